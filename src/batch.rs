@@ -0,0 +1,28 @@
+use rayon::prelude::*;
+use std::sync::Mutex;
+
+/// Run `f` over every input on the current rayon pool, every worker converts with its own
+/// clone of `converter` so the compiled configuration is shared but nothing else is.
+/// The results are in the order of `inputs` whatever order the work was done in
+pub fn par_map<C, T, R, F>(converter: C, inputs: &[T], f: F) -> Vec<R>
+where
+    C: Clone + Send,
+    T: Sync,
+    R: Send,
+    F: Fn(&mut C, &T) -> R + Sync + Send,
+{
+    // the converter only has to be `Send`, workers take turns cloning it
+    let template = Mutex::new(converter);
+    inputs
+        .par_iter()
+        .map_init(
+            || {
+                template
+                    .lock()
+                    .expect("cloning the converter never panics")
+                    .clone()
+            },
+            |converter, input| f(converter, input),
+        )
+        .collect()
+}
@@ -0,0 +1,81 @@
+use crate::error::Px2RemError;
+use crate::transform::Px2RemOption;
+use serde::de::DeserializeOwned;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Config files looked up in every directory, in order of precedence,
+/// `package.json` is only used when it has a `px2rem` key
+pub const CONFIG_FILES: [&str; 4] = [
+    ".px2remrc",
+    "px2rem.config.json",
+    "px2rem.config.toml",
+    "package.json",
+];
+
+/// Load the option from a config file with postcss-pxtorem compatible keys,
+/// `.toml` files are read as TOML, `package.json` from its `px2rem` key,
+/// `.px2remrc` as either JSON or TOML and anything else as JSON
+pub fn load(path: &Path) -> Result<Px2RemOption, Px2RemError> {
+    Ok(load_file(path)?.unwrap_or_default())
+}
+
+/// Search `dir` and its ancestors for the nearest config file
+pub fn find(dir: &Path) -> Result<Option<(PathBuf, Px2RemOption)>, Px2RemError> {
+    for dir in dir.ancestors() {
+        for name in CONFIG_FILES {
+            let path = dir.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            if let Some(option) = load_file(&path)? {
+                return Ok(Some((path, option)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// `None` if `path` is a `package.json` without a `px2rem` key
+fn load_file(path: &Path) -> Result<Option<Px2RemOption>, Px2RemError> {
+    let content = fs::read_to_string(path)?;
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    if file_name == "package.json" {
+        let mut package: serde_json::Value = from_json(path, &content)?;
+        return match package.get_mut("px2rem").map(serde_json::Value::take) {
+            Some(option) => serde_json::from_value(option).map(Some).map_err(|source| {
+                Px2RemError::InvalidJsonConfig {
+                    path: path.to_path_buf(),
+                    source,
+                }
+            }),
+            None => Ok(None),
+        };
+    }
+    let is_toml = path.extension().is_some_and(|ext| ext == "toml")
+        || (file_name == ".px2remrc" && !content.trim_start().starts_with('{'));
+    if is_toml {
+        from_toml(path, &content).map(Some)
+    } else {
+        from_json(path, &content).map(Some)
+    }
+}
+
+fn from_json<T: DeserializeOwned>(path: &Path, content: &str) -> Result<T, Px2RemError> {
+    serde_json::from_str(content).map_err(|source| Px2RemError::InvalidJsonConfig {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn from_toml<T: DeserializeOwned>(path: &Path, content: &str) -> Result<T, Px2RemError> {
+    toml::from_str(content).map_err(|source| Px2RemError::InvalidTomlConfig {
+        path: path.to_path_buf(),
+        source,
+    })
+}
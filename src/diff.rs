@@ -0,0 +1,110 @@
+use std::fmt::Write;
+
+/// Replace the bytes between `start` and `end` of the source with `text`,
+/// an insertion has `start == end`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// Apply `edits`, ordered by offset and not overlapping, to `source`
+pub fn apply_edits(source: &str, edits: &[Edit]) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+    for edit in edits.iter() {
+        output.push_str(&source[cursor..edit.start]);
+        output.push_str(&edit.text);
+        cursor = edit.end;
+    }
+    output.push_str(&source[cursor..]);
+    output
+}
+
+/// A unified diff of `source` before and after `edits` without context lines,
+/// so every edited declaration gets a hunk of its own unless they share a line.
+/// Empty when there is no edit
+pub fn unified_diff(path: &str, source: &str, edits: &[Edit]) -> String {
+    if edits.is_empty() {
+        return String::new();
+    }
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |offset: usize| match line_starts.binary_search(&offset) {
+        Ok(line) => line,
+        Err(next) => next - 1,
+    };
+    let line_end = |line: usize| line_starts.get(line + 1).copied().unwrap_or(source.len());
+
+    // edits touching the same lines end up in the same hunk
+    let mut hunks: Vec<(usize, usize, &[Edit])> = vec![];
+    let mut from = 0;
+    for (i, edit) in edits.iter().enumerate() {
+        let first = line_of(edit.start);
+        let last = line_of(edit.end);
+        match hunks.last_mut() {
+            Some((_, hunk_last, hunk_edits)) if first <= *hunk_last => {
+                *hunk_last = (*hunk_last).max(last);
+                *hunk_edits = &edits[from..=i];
+            }
+            _ => {
+                from = i;
+                hunks.push((first, last, &edits[i..=i]));
+            }
+        }
+    }
+
+    let path = path.trim_start_matches('/');
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let mut delta = 0isize;
+    for (first, last, hunk_edits) in hunks {
+        let offset = line_starts[first];
+        let old = &source[offset..line_end(last)];
+        let shifted = hunk_edits
+            .iter()
+            .map(|edit| Edit {
+                start: edit.start - offset,
+                end: edit.end - offset,
+                text: edit.text.clone(),
+            })
+            .collect::<Vec<_>>();
+        let new = apply_edits(old, &shifted);
+        let (old_count, new_count) = (
+            old.split_inclusive('\n').count(),
+            new.split_inclusive('\n').count(),
+        );
+        let old_start = first + 1;
+        let new_start = (old_start as isize + delta) as usize;
+        delta += new_count as isize - old_count as isize;
+        writeln!(
+            diff,
+            "@@ -{} +{} @@",
+            range(old_start, old_count),
+            range(new_start, new_count)
+        )
+        .expect("writing to a string never fails");
+        write_lines(&mut diff, '-', old);
+        write_lines(&mut diff, '+', &new);
+    }
+    diff
+}
+
+fn range(start: usize, count: usize) -> String {
+    if count == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, count)
+    }
+}
+
+fn write_lines(diff: &mut String, prefix: char, text: &str) {
+    for line in text.split_inclusive('\n') {
+        diff.push(prefix);
+        diff.push_str(line);
+    }
+    if !text.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
@@ -0,0 +1,118 @@
+/// Comments that disable the conversion locally:
+/// `/* px2rem-disable-next-line */` before a rule, at-rule or declaration,
+/// `/* px2rem-disable */ ... /* px2rem-enable */` around them,
+/// and `/* no */` at the end of a declaration
+#[derive(Debug, Default, Clone)]
+pub struct Directives {
+    /// byte offset where every line starts
+    line_starts: Vec<usize>,
+    /// lines right after a `px2rem-disable-next-line`
+    disabled_lines: Vec<usize>,
+    /// byte ranges between `px2rem-disable` and `px2rem-enable`
+    disabled_ranges: Vec<(usize, usize)>,
+    /// byte offsets where the text a `no` comment trails ends,
+    /// whitespace and a semicolon between them are skipped
+    no_comments: Vec<usize>,
+}
+
+impl Directives {
+    pub fn parse(source: &str) -> Self {
+        let mut directives = Directives {
+            line_starts: std::iter::once(0)
+                .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
+            ..Default::default()
+        };
+        let mut disabled_from = None;
+        for (start, end, text) in comments(source) {
+            match text {
+                "px2rem-disable-next-line" => {
+                    directives.disabled_lines.push(directives.line(end) + 1);
+                }
+                "px2rem-disable" => {
+                    disabled_from.get_or_insert(end);
+                }
+                "px2rem-enable" => {
+                    if let Some(from) = disabled_from.take() {
+                        directives.disabled_ranges.push((from, start));
+                    }
+                }
+                "no" => {
+                    let before = source[..start].trim_end();
+                    let before = before.strip_suffix(';').unwrap_or(before).trim_end();
+                    directives.no_comments.push(before.len());
+                }
+                _ => {}
+            }
+        }
+        if let Some(from) = disabled_from {
+            directives.disabled_ranges.push((from, source.len()));
+        }
+        directives
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.disabled_lines.is_empty()
+            && self.disabled_ranges.is_empty()
+            && self.no_comments.is_empty()
+    }
+
+    /// Whether the node starting at byte offset `start` must be left as it is
+    pub fn is_disabled(&self, start: usize) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        self.disabled_ranges
+            .iter()
+            .any(|&(from, to)| from <= start && start < to)
+            || self.disabled_lines.contains(&self.line(start))
+    }
+
+    /// Whether the declaration between `start` and `end` must be left as it is,
+    /// either disabled like any other node or directly followed by `/* no */`
+    pub fn is_declaration_disabled(&self, start: usize, end: usize) -> bool {
+        self.is_disabled(start)
+            || self
+                .no_comments
+                .iter()
+                .any(|&trailed| start < trailed && trailed <= end)
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        }
+    }
+}
+
+/// `(start, end, trimmed text)` of every comment outside of strings
+fn comments(source: &str) -> Vec<(usize, usize, &str)> {
+    let bytes = source.as_bytes();
+    let mut comments = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = source[i + 2..]
+                    .find("*/")
+                    .map_or(source.len(), |len| i + 2 + len + 2);
+                let text = source[i + 2..end].trim_end_matches("*/").trim();
+                comments.push((i, end, text));
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+    comments
+}
@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Px2RemError {
+    #[error("invalid regexp `{pattern}` in selector_black_list: {source}")]
+    InvalidSelectorBlackList {
+        pattern: String,
+        source: regex::Error,
+    },
+    #[error("invalid regexp `{pattern}` in selector_white_list: {source}")]
+    InvalidSelectorWhiteList {
+        pattern: String,
+        source: regex::Error,
+    },
+    #[error("unsupported regexp `{pattern}`: {reason}")]
+    InvalidJsRegexp { pattern: String, reason: String },
+    #[error("invalid exclude regexp `{pattern}`: {source}")]
+    InvalidExcludeRegexp {
+        pattern: String,
+        source: regex::Error,
+    },
+    #[error("invalid exclude glob `{pattern}`: {source}")]
+    InvalidExcludeGlob {
+        pattern: String,
+        source: glob::PatternError,
+    },
+    #[error("unexpected declaration `{prop}: {value}` outside of any rule")]
+    TopLevelDeclaration { prop: String, value: String },
+    #[error("invalid config {}: {source}", path.display())]
+    InvalidJsonConfig {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("invalid config {}: {source}", path.display())]
+    InvalidTomlConfig {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid source map: {0}")]
+    InvalidSourceMap(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
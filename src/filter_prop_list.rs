@@ -1,8 +1,11 @@
 use crate::regex;
+use crate::transform::MatchList;
+use aho_corasick::AhoCorasick;
 use smol_str::SmolStr;
-use std::rc::Rc;
+use std::collections::HashSet;
+use std::sync::Arc;
 
-pub fn exact(list: Rc<Vec<String>>) -> Vec<SmolStr> {
+pub fn exact(list: Arc<Vec<String>>) -> Vec<SmolStr> {
     list.iter()
         .filter(|prop| {
             let reg = regex!("^[^*!]+$");
@@ -12,7 +15,7 @@ pub fn exact(list: Rc<Vec<String>>) -> Vec<SmolStr> {
         .collect::<Vec<_>>()
 }
 
-pub fn contain(list: Rc<Vec<String>>) -> Vec<SmolStr> {
+pub fn contain(list: Arc<Vec<String>>) -> Vec<SmolStr> {
     list.iter()
         .filter(|prop| {
             let reg = regex!(r#"^\*.+\*$"#);
@@ -22,7 +25,7 @@ pub fn contain(list: Rc<Vec<String>>) -> Vec<SmolStr> {
         .collect::<Vec<_>>()
 }
 
-pub fn ends_with(list: Rc<Vec<String>>) -> Vec<SmolStr> {
+pub fn ends_with(list: Arc<Vec<String>>) -> Vec<SmolStr> {
     list.iter()
         .filter(|prop| {
             let reg = regex!(r"^\*[^*]+$");
@@ -32,7 +35,7 @@ pub fn ends_with(list: Rc<Vec<String>>) -> Vec<SmolStr> {
         .collect::<Vec<_>>()
 }
 
-pub fn starts_with(list: Rc<Vec<String>>) -> Vec<SmolStr> {
+pub fn starts_with(list: Arc<Vec<String>>) -> Vec<SmolStr> {
     list.iter()
         .filter(|prop| {
             let reg = regex!(r"^[^*!]+\*$");
@@ -42,7 +45,7 @@ pub fn starts_with(list: Rc<Vec<String>>) -> Vec<SmolStr> {
         .collect::<Vec<_>>()
 }
 
-pub fn not_exact(list: Rc<Vec<String>>) -> Vec<SmolStr> {
+pub fn not_exact(list: Arc<Vec<String>>) -> Vec<SmolStr> {
     list.iter()
         .filter(|prop| {
             let reg = regex!("^![^*].*$");
@@ -52,7 +55,7 @@ pub fn not_exact(list: Rc<Vec<String>>) -> Vec<SmolStr> {
         .collect::<Vec<_>>()
 }
 
-pub fn not_contain(list: Rc<Vec<String>>) -> Vec<SmolStr> {
+pub fn not_contain(list: Arc<Vec<String>>) -> Vec<SmolStr> {
     list.iter()
         .filter(|prop| {
             let reg = regex!(r#"^!\*.+\*$"#);
@@ -62,7 +65,7 @@ pub fn not_contain(list: Rc<Vec<String>>) -> Vec<SmolStr> {
         .collect::<Vec<_>>()
 }
 
-pub fn not_ends_with(list: Rc<Vec<String>>) -> Vec<SmolStr> {
+pub fn not_ends_with(list: Arc<Vec<String>>) -> Vec<SmolStr> {
     list.iter()
         .filter(|prop| {
             let reg = regex!(r"^!\*[^*]+$");
@@ -72,7 +75,7 @@ pub fn not_ends_with(list: Rc<Vec<String>>) -> Vec<SmolStr> {
         .collect::<Vec<_>>()
 }
 
-pub fn not_starts_with(list: Rc<Vec<String>>) -> Vec<SmolStr> {
+pub fn not_starts_with(list: Arc<Vec<String>>) -> Vec<SmolStr> {
     list.iter()
         .filter(|prop| {
             let reg = regex!(r"^![^*]+\*");
@@ -90,3 +93,87 @@ pub fn not_starts_with(list: Rc<Vec<String>>) -> Vec<SmolStr> {
 //         })
 //         .collect::<Vec<_>>()
 // }
+
+#[derive(Debug, Clone, Copy)]
+enum Position {
+    Contain,
+    StartsWith,
+    EndsWith,
+}
+
+/// The prop list compiled into a single matcher: hash sets for the exact names and one
+/// automaton for every `*` pattern, negated or not, so a prop is matched in a single scan
+#[derive(Debug, Clone)]
+pub struct PropMatcher {
+    all_match: bool,
+    has_wild: bool,
+    exact: HashSet<SmolStr>,
+    not_exact: HashSet<SmolStr>,
+    automaton: Option<AhoCorasick>,
+    /// where each pattern of the automaton has to be found, and whether it negates
+    positions: Vec<Vec<(Position, bool)>>,
+}
+
+impl PropMatcher {
+    pub fn new(match_list: &MatchList, has_wild: bool, all_match: bool) -> Self {
+        let mut patterns: Vec<SmolStr> = vec![];
+        let mut positions: Vec<Vec<(Position, bool)>> = vec![];
+        let lists = [
+            (&match_list.contain_list, Position::Contain, false),
+            (&match_list.starts_with_list, Position::StartsWith, false),
+            (&match_list.ends_with_list, Position::EndsWith, false),
+            (&match_list.not_contain_list, Position::Contain, true),
+            (&match_list.not_starts_list, Position::StartsWith, true),
+            (&match_list.not_ends_list, Position::EndsWith, true),
+        ];
+        for (list, position, negated) in lists {
+            for pattern in list.iter() {
+                // the automaton reports every pattern, so each one is added once
+                match patterns.iter().position(|p| p == pattern) {
+                    Some(i) => positions[i].push((position, negated)),
+                    None => {
+                        patterns.push(pattern.clone());
+                        positions.push(vec![(position, negated)]);
+                    }
+                }
+            }
+        }
+        Self {
+            all_match,
+            has_wild,
+            exact: match_list.exact_list.iter().cloned().collect(),
+            not_exact: match_list.not_exact_list.iter().cloned().collect(),
+            automaton: if patterns.is_empty() {
+                None
+            } else {
+                Some(AhoCorasick::new(patterns.iter().map(|p| p.as_str())))
+            },
+            positions,
+        }
+    }
+
+    pub fn is_match(&self, prop: &str) -> bool {
+        if self.all_match {
+            return true;
+        }
+        let (mut matched, mut negated) = (false, false);
+        if let Some(automaton) = &self.automaton {
+            for found in automaton.find_overlapping_iter(prop) {
+                for &(position, negates) in self.positions[found.pattern()].iter() {
+                    let hit = match position {
+                        Position::Contain => true,
+                        Position::StartsWith => found.start() == 0,
+                        Position::EndsWith => found.end() == prop.len(),
+                    };
+                    if hit && negates {
+                        negated = true;
+                    } else if hit {
+                        matched = true;
+                    }
+                }
+            }
+        }
+        (self.has_wild || matched || self.exact.contains(prop))
+            && !(negated || self.not_exact.contains(prop))
+    }
+}
@@ -1,3 +1,12 @@
+pub mod batch;
+pub mod config;
+pub mod diff;
+pub mod directive;
+pub mod error;
 pub mod filter_prop_list;
+pub mod lint;
 pub(crate) mod marco_utils;
+pub mod selector;
+pub mod source_map;
+pub mod stats;
 pub mod transform;
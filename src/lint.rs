@@ -0,0 +1,47 @@
+use std::fmt;
+
+/// A declaration or at-rule whose value would be converted, see [`crate::transform::Px2Rem::lint`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    /// the property, or the at-rule name with its `@` for at-rule params
+    pub prop: String,
+    pub value: String,
+    /// the value after the conversion
+    pub suggested: String,
+}
+
+impl Violation {
+    /// Locate the violation found at byte offset `offset` of `source`
+    pub(crate) fn at(
+        source: &str,
+        offset: usize,
+        prop: String,
+        value: String,
+        suggested: String,
+    ) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            prop,
+            value,
+            suggested,
+        }
+    }
+}
+
+/// e.g. `3:5: font-size: 16px, use 1rem`
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}, use {}",
+            self.line, self.column, self.prop, self.value, self.suggested
+        )
+    }
+}
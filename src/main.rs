@@ -1,34 +1,686 @@
-#![allow(clippy::all)]
-#![allow(dead_code)]
-use postcss_px2rem::transform::{Px2Rem, Px2RemOption, SimplePrettier};
+use clap::{ArgEnum, Parser};
+use postcss_px2rem::batch;
+use postcss_px2rem::config;
+use postcss_px2rem::diff;
+use postcss_px2rem::error::Px2RemError;
+use postcss_px2rem::source_map::{self, SourceMap, SourceMapBuilder};
+use postcss_px2rem::stats::Stats;
+use postcss_px2rem::transform::{
+    Exclude, LosslessPrinter, MinifiedPrinter, Px2RemOption, SimplePrettier, SourceUnit,
+    StringOrRegexp, TargetUnit, UnitConverter, UnitConverterOption,
+};
 use recursive_parser::{parse, visitor::VisitMut, WrapString};
-use std::time::Instant;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    process,
+};
+
+/// Convert px units in stylesheets to rem
+#[derive(Parser, Debug)]
+#[clap(name = "px2rem", version)]
+struct Cli {
+    /// Files, directories or glob patterns to convert
+    #[clap(required = true)]
+    inputs: Vec<String>,
+
+    /// Read options from this config file instead of searching for
+    /// `.px2remrc`, `px2rem.config.json` or a `px2rem` key in `package.json`,
+    /// command line flags take precedence over the config
+    #[clap(short, long)]
+    config: Option<PathBuf>,
+
+    /// The root element font size
+    #[clap(long)]
+    root_value: Option<f64>,
+
+    /// The unit to convert from, one of px, pt, pc, in, cm, mm and Q
+    #[clap(long, default_value = "px")]
+    from_unit: SourceUnit,
+
+    /// The unit to convert to, one of rem, em, vw, vh, vmin, vmax, rpx and px
+    #[clap(long, default_value = "rem")]
+    to_unit: TargetUnit,
+
+    /// The width of the design draft, the base of vw and rpx, 320 by default
+    #[clap(long)]
+    viewport_width: Option<f64>,
+
+    /// The height of the design draft, the base of vh, 568 by default
+    #[clap(long)]
+    viewport_height: Option<f64>,
+
+    /// The decimal numbers to allow the rem units to grow to
+    #[clap(long)]
+    unit_precision: Option<i32>,
+
+    /// Properties that can change from px to rem, separated by commas.
+    /// Supports `*` wildcards and `!` negations
+    #[clap(long, use_value_delimiter = true)]
+    prop_list: Option<Vec<String>>,
+
+    /// Selectors to ignore and leave as px, matched as substrings,
+    /// or as regexps when written as JavaScript regex literals, e.g. `/^body$/i`
+    #[clap(long)]
+    selector_black_list: Vec<String>,
+
+    /// Selectors to ignore and leave as px, matched as regular expressions
+    #[clap(long)]
+    selector_black_list_regexp: Vec<String>,
+
+    /// Only convert the rules matching one of these selectors, matched like the black list,
+    /// `.mobile-root *` matches the selectors under `.mobile-root`
+    #[clap(long)]
+    selector_white_list: Vec<String>,
+
+    /// Match the selector black and white lists against every selector of a selector list,
+    /// on class, id and element boundaries, and split the rules only partly converted
+    #[clap(long)]
+    split_selector_list: bool,
+
+    /// Keep the px declaration and add the rem one as a fallback after it
+    #[clap(long)]
+    no_replace: bool,
+
+    /// Also convert px in media queries
+    #[clap(long)]
+    media_query: bool,
+
+    /// Also convert px in the params of these at-rules, separated by commas,
+    /// e.g. `container,supports,custom-media`
+    #[clap(long, use_value_delimiter = true)]
+    at_rule_list: Option<Vec<String>>,
+
+    /// Do not convert values below this number of pixels
+    #[clap(long)]
+    min_pixel_value: Option<f64>,
+
+    /// Leave files whose path matches this glob untouched, e.g. `**/node_modules/**`
+    #[clap(long, conflicts_with = "exclude-regexp")]
+    exclude: Option<String>,
+
+    /// Leave files whose path matches this regular expression untouched
+    #[clap(long)]
+    exclude_regexp: Option<String>,
+
+    /// Number of spaces used to indent the output
+    #[clap(long, default_value = "2")]
+    indent: usize,
+
+    /// How to print the output, `lossless` keeps the input as it is except for the converted values,
+    /// `minified` writes every stylesheet on a single line
+    #[clap(long, arg_enum, default_value = "pretty")]
+    format: Format,
+
+    /// Write converted files into this directory instead of stdout
+    #[clap(short, long, conflicts_with = "in-place")]
+    out_dir: Option<PathBuf>,
+
+    /// Overwrite the input files with the converted output
+    #[clap(short, long)]
+    in_place: bool,
+
+    /// Emit a source map, either inline as a `sourceMappingURL` comment or as a `.map` file
+    /// next to the output, which requires `--out-dir` or `--in-place`.
+    /// A source map the input already refers to is chained
+    #[clap(long, arg_enum)]
+    source_map: Option<SourceMapMode>,
+
+    /// Print a unified diff of the converted values instead of writing anything,
+    /// exits with 1 when any value would be converted
+    #[clap(long, conflicts_with_all = &["out-dir", "in-place", "source-map"])]
+    dry_run: bool,
+
+    /// Report every px value that would be converted instead of converting it,
+    /// exits with 1 when there is any
+    #[clap(long, conflicts_with_all = &["out-dir", "in-place", "source-map", "dry-run"])]
+    check: bool,
+
+    /// Number of files converted in parallel, all the cores by default
+    #[clap(short, long)]
+    jobs: Option<usize>,
+
+    /// Write what was converted and skipped, in total and per file, to this JSON file
+    #[clap(long)]
+    report: Option<PathBuf>,
+}
+
+/// The `--report` output
+#[derive(Serialize, Default)]
+struct Report {
+    total: Stats,
+    files: BTreeMap<String, Stats>,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Pretty,
+    Lossless,
+    Minified,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+enum SourceMapMode {
+    Inline,
+    File,
+}
+
+impl Cli {
+    /// Merge the flags into the option loaded from the config file
+    fn option(&self, config: Px2RemOption) -> Result<Px2RemOption, Px2RemError> {
+        let mut selector_black_list = self
+            .selector_black_list
+            .iter()
+            .map(|selector| StringOrRegexp::from_js(selector))
+            .collect::<Result<Vec<_>, _>>()?;
+        selector_black_list.extend(
+            self.selector_black_list_regexp
+                .iter()
+                .cloned()
+                .map(StringOrRegexp::Regexp),
+        );
+        Ok(Px2RemOption {
+            root_value: self.root_value.or(config.root_value),
+            root_value_resolver: None,
+            unit_precision: self.unit_precision.or(config.unit_precision),
+            selector_black_list: if selector_black_list.is_empty() {
+                config.selector_black_list
+            } else {
+                Some(selector_black_list)
+            },
+            selector_white_list: if self.selector_white_list.is_empty() {
+                config.selector_white_list
+            } else {
+                Some(
+                    self.selector_white_list
+                        .iter()
+                        .map(|selector| StringOrRegexp::from_js(selector))
+                        .collect::<Result<Vec<_>, _>>()?,
+                )
+            },
+            split_selector_list: if self.split_selector_list {
+                Some(true)
+            } else {
+                config.split_selector_list
+            },
+            prop_list: self.prop_list.clone().or(config.prop_list),
+            replace: if self.no_replace {
+                Some(false)
+            } else {
+                config.replace
+            },
+            media_query: if self.media_query {
+                Some(true)
+            } else {
+                config.media_query
+            },
+            at_rule_list: self.at_rule_list.clone().or(config.at_rule_list),
+            min_pixel_value: self.min_pixel_value.or(config.min_pixel_value),
+            exclude: self
+                .exclude
+                .clone()
+                .map(Exclude::Glob)
+                .or_else(|| self.exclude_regexp.clone().map(Exclude::Regexp))
+                .or(config.exclude),
+        })
+    }
+
+    fn load_config(&self) -> Result<Px2RemOption, Px2RemError> {
+        match &self.config {
+            Some(path) => config::load(path),
+            None => Ok(config::find(&std::env::current_dir()?)?
+                .map(|(_, option)| option)
+                .unwrap_or_default()),
+        }
+    }
+}
+
+/// A stylesheet to convert, `relative` is the path used under `--out-dir`
+struct Input {
+    path: PathBuf,
+    relative: PathBuf,
+}
+
+impl Input {
+    /// `base` is the directory or glob base the file was found under
+    fn new(path: PathBuf, base: &Path) -> Self {
+        let relative = match path.strip_prefix(base) {
+            Ok(relative) if relative.components().next().is_some() => relative.to_path_buf(),
+            _ => path
+                .file_name()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| path.clone()),
+        };
+        Self { path, relative }
+    }
+}
 
 fn main() {
-    let _css = ".rule { margin: 12px calc(100% - 14PX); height: calc(100% - 20px); font-size: 12Px; line-height: 16px; }";
-    let css2 = include_str!("../assets/bootstrap.css");
-    let start = Instant::now();
-    let mut root = parse(css2, None);
-    println!("{:?}", start.elapsed());
-
-    let start = Instant::now();
-    let mut px_to_rem = Px2Rem::new(Px2RemOption {
-        prop_list: Some(vec!["*".to_string()]),
-        ..Px2RemOption::default()
+    let cli = Cli::parse();
+    if cli.source_map == Some(SourceMapMode::File) && cli.out_dir.is_none() && !cli.in_place {
+        eprintln!("px2rem: --source-map file requires --out-dir or --in-place");
+        process::exit(2);
+    }
+    if cli.source_map.is_some() && cli.format != Format::Pretty {
+        eprintln!("px2rem: --source-map is only supported with --format pretty");
+        process::exit(2);
+    }
+    let inputs = match collect_inputs(&cli.inputs) {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            eprintln!("px2rem: {}", err);
+            process::exit(2);
+        }
+    };
+    if let Some(out_dir) = &cli.out_dir {
+        if let Some((first, second)) = find_collision(&inputs) {
+            eprintln!(
+                "px2rem: {} and {} would both be written to {}",
+                first.path.display(),
+                second.path.display(),
+                out_dir.join(&first.relative).display()
+            );
+            process::exit(2);
+        }
+    }
+    let option = match cli.load_config().and_then(|config| cli.option(config)) {
+        Ok(option) => option,
+        Err(err) => {
+            eprintln!("px2rem: {}", err);
+            process::exit(2);
+        }
+    };
+    let px_to_rem = match UnitConverter::try_new(UnitConverterOption {
+        from: Some(cli.from_unit),
+        to: Some(cli.to_unit),
+        viewport_width: cli.viewport_width,
+        viewport_height: cli.viewport_height,
+        common: option,
+    }) {
+        Ok(px_to_rem) => px_to_rem,
+        Err(err) => {
+            eprintln!("px2rem: {}", err);
+            process::exit(2);
+        }
+    };
+    let pool = match rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.jobs.unwrap_or(0))
+        .build()
+    {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("px2rem: {}", err);
+            process::exit(2);
+        }
+    };
+    // files are converted in parallel, what they print is buffered and written in input order
+    let results = pool.install(|| {
+        batch::par_map(px_to_rem, &inputs, |px_to_rem, input| {
+            let mut output = vec![];
+            let result = if cli.dry_run {
+                diff_file(px_to_rem, input, &mut output)
+            } else if cli.check {
+                check_file(px_to_rem, input, &mut output)
+            } else {
+                process_file(&cli, px_to_rem, input, &mut output).map(|_| false)
+            };
+            (output, result, px_to_rem.take_stats())
+        })
     });
-    px_to_rem.visit_root(&mut root);
-    println!("{:?}", start.elapsed());
-    // let mut writer = SimplePrettier::new(std::io::stdout(), 2);
-    // writer.visit_root(&mut root).unwrap();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut failed = false;
+    let mut changed = false;
+    let mut report = Report::default();
+    for (input, (output, result, stats)) in inputs.iter().zip(results) {
+        if let Err(err) = stdout.write_all(&output) {
+            eprintln!("px2rem: {}", err);
+            process::exit(1);
+        }
+        match result {
+            Ok(found) => changed |= found,
+            Err(err) => {
+                eprintln!("px2rem: {}: {}", input.path.display(), err);
+                failed = true;
+            }
+        }
+        report.total.merge(&stats);
+        report
+            .files
+            .insert(input.path.to_string_lossy().to_string(), stats);
+    }
+    if let Some(path) = &cli.report {
+        let json = serde_json::to_string_pretty(&report).expect("a report is always serializable");
+        if let Err(err) = fs::write(path, json + "\n") {
+            eprintln!("px2rem: {}: {}", path.display(), err);
+            failed = true;
+        }
+    }
+    if failed || changed {
+        process::exit(1);
+    }
+}
+
+/// Print the values of `input` that would be converted, returns whether there is any
+fn check_file(
+    px_to_rem: &mut UnitConverter,
+    input: &Input,
+    stdout: &mut impl Write,
+) -> Result<bool, Px2RemError> {
+    let css = fs::read_to_string(&input.path)?;
+    let from = input.path.to_string_lossy();
+    if px_to_rem.is_excluded(&from) {
+        return Ok(false);
+    }
+    px_to_rem.set_from(Some(&from));
+    let mut root = parse(&css, None);
+    let violations = px_to_rem.lint(&mut root, &css);
+    for violation in violations.iter() {
+        writeln!(stdout, "{}:{}", from, violation)?;
+    }
+    Ok(!violations.is_empty())
+}
+
+/// Print the diff the conversion of `input` would make, one hunk per converted declaration
+/// whatever the `--format`, returns whether any value would be converted
+fn diff_file(
+    px_to_rem: &mut UnitConverter,
+    input: &Input,
+    stdout: &mut impl Write,
+) -> Result<bool, Px2RemError> {
+    let css = fs::read_to_string(&input.path)?;
+    let from = input.path.to_string_lossy();
+    if px_to_rem.is_excluded(&from) {
+        return Ok(false);
+    }
+    px_to_rem.set_from(Some(&from));
+    px_to_rem.set_source(&css);
+    let converted_before = px_to_rem.stats().converted;
+    let mut root = parse(&css, None);
+    px_to_rem.try_visit_root(&mut root)?;
+    let mut writer = LosslessPrinter::new(io::sink(), &css);
+    writer.visit_root(&mut root)?;
+    let diff = diff::unified_diff(&from, &css, writer.edits());
+    stdout.write_all(diff.as_bytes())?;
+    Ok(px_to_rem.stats().converted > converted_before)
+}
+
+fn process_file(
+    cli: &Cli,
+    px_to_rem: &mut UnitConverter,
+    input: &Input,
+    stdout: &mut impl Write,
+) -> Result<(), Px2RemError> {
+    let css = fs::read_to_string(&input.path)?;
+    let from = input.path.to_string_lossy();
+    let target = if cli.in_place {
+        Some(input.path.clone())
+    } else {
+        cli.out_dir
+            .as_ref()
+            .map(|out_dir| out_dir.join(&input.relative))
+    };
+    let output = if px_to_rem.is_excluded(&from) {
+        if cli.in_place {
+            return Ok(());
+        }
+        css
+    } else {
+        px_to_rem.set_from(Some(&from));
+        px_to_rem.set_source(&css);
+        // `sources` are resolved against the map, which sits next to the output
+        let map_dir = target
+            .as_ref()
+            .map(|target| target.parent().unwrap_or_else(|| Path::new("")));
+        let source_name = match map_dir {
+            Some(map_dir) => relative_url(map_dir, &input.path)?,
+            None => from.to_string(),
+        };
+        let builder = cli
+            .source_map
+            .map(|_| SourceMapBuilder::new(&source_name, &css));
+        let (mut output, map) = transform(&css, px_to_rem, cli, builder)?;
+        if let Some(mut map) = map {
+            if let Some((incoming_dir, mut incoming)) = incoming_source_map(&input.path, &css)? {
+                for source in incoming.sources.iter_mut() {
+                    if !source.contains("://") && !Path::new(source.as_str()).is_absolute() {
+                        let path = incoming_dir.join(source.as_str());
+                        *source = match map_dir {
+                            Some(map_dir) => relative_url(map_dir, &path)?,
+                            None => path_to_url(&path),
+                        };
+                    }
+                }
+                map = map.chain(&incoming)?;
+            }
+            match (cli.source_map, &target) {
+                (Some(SourceMapMode::File), Some(target)) => {
+                    let file_name = target
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string());
+                    map.file = file_name.clone();
+                    let map_name = format!("{}.map", file_name.unwrap_or_default());
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(target.with_file_name(&map_name), map.to_json())?;
+                    output += &format!("/*# sourceMappingURL={} */\n", map_name);
+                }
+                _ => {
+                    output += &map.to_inline_comment();
+                    output.push('\n');
+                }
+            }
+        }
+        output
+    };
+    if let Some(target) = target {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, output)?;
+    } else {
+        stdout.write_all(output.as_bytes())?;
+    }
+    Ok(())
+}
+
+fn transform(
+    css: &str,
+    px_to_rem: &mut UnitConverter,
+    cli: &Cli,
+    source_map: Option<SourceMapBuilder>,
+) -> Result<(String, Option<SourceMap>), Px2RemError> {
+    let mut root = parse(css, None);
+    px_to_rem.try_visit_root(&mut root)?;
+    if cli.format == Format::Lossless {
+        let mut writer = LosslessPrinter::new(WrapString::default(), css);
+        writer.visit_root(&mut root)?;
+        return Ok((writer.writer.0, None));
+    }
+    if cli.format == Format::Minified {
+        let mut writer = MinifiedPrinter::new(WrapString::default());
+        writer.visit_root(&mut root)?;
+        return Ok((writer.writer.0, None));
+    }
+    let mut writer = SimplePrettier::new(WrapString::default(), cli.indent);
+    if let Some(builder) = source_map {
+        writer = writer.with_source_map(builder);
+    }
+    writer.visit_root(&mut root)?;
+    let map = writer.source_map(None);
+    Ok((writer.writer.0, map))
+}
+
+/// The source map `css` refers to with a `sourceMappingURL` comment, either inline
+/// or as a file relative to the stylesheet, a missing file is ignored.
+/// Comes with the directory its `sources` are relative to
+fn incoming_source_map(
+    path: &Path,
+    css: &str,
+) -> Result<Option<(PathBuf, SourceMap)>, Px2RemError> {
+    let url = match source_map::find_url(css) {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    if url.starts_with("data:") {
+        return SourceMap::from_data_url(url).map(|map| Some((dir.to_path_buf(), map)));
+    }
+    let map_path = dir.join(url);
+    match fs::read_to_string(&map_path) {
+        Ok(json) => SourceMap::from_json(&json).map(|map| {
+            let map_dir = map_path.parent().unwrap_or_else(|| Path::new(""));
+            Some((map_dir.to_path_buf(), map))
+        }),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The url of `path` relative to the directory `dir`, e.g. `../src/a.css` for `dist`
+fn relative_url(dir: &Path, path: &Path) -> io::Result<String> {
+    let (dir, path) = (absolute(dir)?, absolute(path)?);
+    let common = dir
+        .components()
+        .zip(path.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let relative = std::iter::repeat_n(Component::ParentDir, dir.components().count() - common)
+        .chain(path.components().skip(common))
+        .collect::<PathBuf>();
+    Ok(path_to_url(&relative))
+}
+
+/// `path` made absolute against the current directory, with `.` and `..` resolved
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    let mut absolute = PathBuf::new();
+    for component in std::env::current_dir()?.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+    Ok(absolute)
+}
+
+/// `path` with `/` separators whatever the platform
+fn path_to_url(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "/")
+}
+
+/// Expand every input argument into the stylesheets it refers to,
+/// directories are walked recursively for `.css` files.
+/// Under `--out-dir` a file keeps its path relative to the directory or glob base it was
+/// found under, the files given one by one keep their path relative to their common ancestor
+fn collect_inputs(patterns: &[String]) -> io::Result<Vec<Input>> {
+    let files = patterns
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.is_file())
+        .map(|path| match path.parent() {
+            Some(parent) if parent != Path::new("") => fs::canonicalize(parent),
+            _ => fs::canonicalize("."),
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let common_ancestor = files
+        .iter()
+        .skip(1)
+        .fold(files.first().cloned(), |common, dir| {
+            common.map(|common| {
+                common
+                    .components()
+                    .zip(dir.components())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect::<PathBuf>()
+            })
+        });
+    let mut inputs: Vec<Input> = vec![];
+    for pattern in patterns {
+        let path = Path::new(pattern);
+        if path.is_dir() {
+            let mut files = vec![];
+            walk_dir(path, &mut files)?;
+            files.sort();
+            for file in files {
+                inputs.push(Input::new(file, path));
+            }
+        } else if path.is_file() {
+            let relative = match &common_ancestor {
+                Some(common_ancestor) => fs::canonicalize(path)?
+                    .strip_prefix(common_ancestor)
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|_| path.to_path_buf()),
+                None => path.to_path_buf(),
+            };
+            inputs.push(Input {
+                path: path.to_path_buf(),
+                relative,
+            });
+        } else if is_glob(pattern) {
+            let base = glob_base(pattern);
+            let paths = glob::glob(pattern)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            for entry in paths {
+                let file = entry.map_err(io::Error::other)?;
+                if file.is_file() {
+                    inputs.push(Input::new(file, &base));
+                }
+            }
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}: no such file or directory", pattern),
+            ));
+        }
+    }
+    Ok(inputs)
+}
+
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_dir(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "css") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
 }
 
-fn get_transformed_content_new(input: &str, option: Px2RemOption) -> String {
-    let mut root = parse(input, None);
+/// The directories of `pattern` before the first component with a wildcard,
+/// e.g. `src` for `src/**/*.css`
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| !is_glob(&component.as_os_str().to_string_lossy()))
+        .collect()
+}
 
-    let mut px_to_rem = Px2Rem::new(option);
-    px_to_rem.visit_root(&mut root);
-    let wrap_string = WrapString::default();
-    let mut writer = SimplePrettier::new(wrap_string, 4);
-    writer.visit_root(&mut root).unwrap();
-    writer.writer.0
+/// Two different inputs that would be written to the same path under `--out-dir`
+fn find_collision(inputs: &[Input]) -> Option<(&Input, &Input)> {
+    let mut seen: BTreeMap<&Path, &Input> = BTreeMap::new();
+    for input in inputs.iter() {
+        match seen.get(input.relative.as_path()) {
+            Some(first) if first.path != input.path => return Some((first, input)),
+            Some(_) => {}
+            None => {
+                seen.insert(&input.relative, input);
+            }
+        }
+    }
+    None
 }
@@ -5,3 +5,72 @@ macro_rules! regex {
         RE.get_or_init(|| regex::Regex::new($re).unwrap())
     }};
 }
+
+/// Forward the per-stylesheet setters and `VisitMut` of a newtype around `Px2Rem`
+macro_rules! delegate_px2rem {
+    ($name:ident) => {
+        impl $name {
+            /// see [`Px2Rem::set_from`]
+            pub fn set_from(&mut self, from: Option<&str>) {
+                self.0.set_from(from);
+            }
+
+            /// see [`Px2Rem::set_source`]
+            pub fn set_source(&mut self, source: &str) {
+                self.0.set_source(source);
+            }
+
+            /// see [`Px2Rem::is_excluded`]
+            pub fn is_excluded(&self, path: &str) -> bool {
+                self.0.is_excluded(path)
+            }
+
+            /// see [`Px2Rem::stats`]
+            pub fn stats(&self) -> $crate::stats::Stats {
+                self.0.stats()
+            }
+
+            /// see [`Px2Rem::take_stats`]
+            pub fn take_stats(&mut self) -> $crate::stats::Stats {
+                self.0.take_stats()
+            }
+
+            /// see [`Px2Rem::lint`]
+            pub fn lint(
+                &mut self,
+                root: &mut recursive_parser::parser::Root,
+                source: &str,
+            ) -> Vec<$crate::lint::Violation> {
+                self.0.lint(root, source)
+            }
+
+            /// see [`Px2Rem::try_visit_root`]
+            pub fn try_visit_root(
+                &mut self,
+                root: &mut recursive_parser::parser::Root,
+            ) -> Result<(), $crate::error::Px2RemError> {
+                self.0.try_visit_root(root)
+            }
+        }
+
+        impl<'a> recursive_parser::visitor::VisitMut<'a> for $name {
+            fn visit_root(&mut self, root: &mut recursive_parser::parser::Root<'a>) {
+                self.0.visit_root(root);
+            }
+
+            fn visit_rule(&mut self, rule: &mut recursive_parser::parser::Rule<'a>) {
+                self.0.visit_rule(rule);
+            }
+
+            fn visit_at_rule(&mut self, at_rule: &mut recursive_parser::parser::AtRule<'a>) {
+                self.0.visit_at_rule(at_rule);
+            }
+
+            fn visit_declaration(&mut self, decl: &mut recursive_parser::parser::Declaration<'a>) {
+                self.0.visit_declaration(decl);
+            }
+        }
+    };
+}
+
+pub(crate) use delegate_px2rem;
@@ -0,0 +1,43 @@
+/// Split a selector list such as `.a, .b:is(.c, .d)` into its complex selectors,
+/// commas inside parentheses, brackets and strings are not separators
+pub fn split_list(selector: &str) -> Vec<&str> {
+    let mut list = vec![];
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in selector.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth = depth.saturating_sub(1),
+            (None, ',') if depth == 0 => {
+                list.push(selector[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    list.push(selector[start..].trim());
+    list.retain(|selector| !selector.is_empty());
+    list
+}
+
+/// Resolve a nested selector against its parent following CSS Nesting,
+/// `&` is replaced by the parent selector, otherwise the parent becomes an ancestor,
+/// e.g. `.a, .b` and `& .c` resolve to `.a .c, .b .c`
+pub fn resolve_nested(parent: &str, selector: &str) -> String {
+    let parents = split_list(parent);
+    let mut resolved = vec![];
+    for child in split_list(selector) {
+        for parent in parents.iter() {
+            if child.contains('&') {
+                resolved.push(child.replace('&', parent));
+            } else {
+                resolved.push(format!("{} {}", parent, child));
+            }
+        }
+    }
+    resolved.join(", ")
+}
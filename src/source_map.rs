@@ -0,0 +1,306 @@
+use crate::error::Px2RemError;
+use crate::regex;
+use serde::{Deserialize, Serialize};
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// A v3 source map, see <https://sourcemaps.info/spec.html>
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMap {
+    pub version: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sources_content: Option<Vec<Option<String>>>,
+    #[serde(default)]
+    pub names: Vec<String>,
+    pub mappings: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    generated_column: i64,
+    original: Option<Original>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Original {
+    source: i64,
+    line: i64,
+    column: i64,
+    name: Option<i64>,
+}
+
+impl SourceMap {
+    pub fn from_json(json: &str) -> Result<Self, Px2RemError> {
+        let map: SourceMap = serde_json::from_str(json)
+            .map_err(|err| Px2RemError::InvalidSourceMap(err.to_string()))?;
+        if map.version != 3 {
+            return Err(Px2RemError::InvalidSourceMap(format!(
+                "unsupported version {}",
+                map.version
+            )));
+        }
+        decode(&map.mappings)?;
+        Ok(map)
+    }
+
+    /// Parse a `data:application/json;base64,...` url of an inline source map
+    pub fn from_data_url(url: &str) -> Result<Self, Px2RemError> {
+        let data = url
+            .strip_prefix("data:application/json")
+            .and_then(|rest| rest.split_once("base64,"))
+            .map(|(_, data)| data)
+            .ok_or_else(|| Px2RemError::InvalidSourceMap(format!("unsupported url `{}`", url)))?;
+        let json =
+            base64::decode(data).map_err(|err| Px2RemError::InvalidSourceMap(err.to_string()))?;
+        Self::from_json(&String::from_utf8_lossy(&json))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a source map is always serializable")
+    }
+
+    /// The `sourceMappingURL` comment embedding the whole map
+    pub fn to_inline_comment(&self) -> String {
+        format!(
+            "/*# sourceMappingURL=data:application/json;base64,{} */",
+            base64::encode(self.to_json())
+        )
+    }
+
+    /// Map the original positions of `self` further back through `incoming`,
+    /// the map of the step that generated the stylesheet `self` was built from,
+    /// positions `incoming` knows nothing about are dropped
+    pub fn chain(&self, incoming: &SourceMap) -> Result<SourceMap, Px2RemError> {
+        let incoming_lines = decode(&incoming.mappings)?;
+        let lines = decode(&self.mappings)?
+            .into_iter()
+            .map(|segments| {
+                segments
+                    .into_iter()
+                    .filter_map(|segment| {
+                        let original = segment.original?;
+                        let traced = incoming_lines
+                            .get(original.line as usize)?
+                            .iter()
+                            .rev()
+                            .find(|candidate| candidate.generated_column <= original.column)?
+                            .original?;
+                        Some(Segment {
+                            generated_column: segment.generated_column,
+                            original: Some(traced),
+                        })
+                    })
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+        Ok(SourceMap {
+            version: 3,
+            file: self.file.clone(),
+            sources: incoming.sources.clone(),
+            sources_content: incoming.sources_content.clone(),
+            names: incoming.names.clone(),
+            mappings: encode(&lines),
+        })
+    }
+}
+
+/// The url of the last `/*# sourceMappingURL=... */` comment in `css`
+pub fn find_url(css: &str) -> Option<&str> {
+    regex!(r"/\*[#@]\s*sourceMappingURL=(\S+)\s*\*/")
+        .captures_iter(css)
+        .last()
+        .and_then(|caps| caps.get(1))
+        .map(|url| url.as_str())
+}
+
+/// Collects the mappings of a single source while the printer writes the output
+#[derive(Debug)]
+pub struct SourceMapBuilder {
+    source_name: String,
+    source: String,
+    /// byte offset where every line of `source` starts
+    line_starts: Vec<usize>,
+    lines: Vec<Vec<Segment>>,
+}
+
+impl SourceMapBuilder {
+    pub fn new(source_name: &str, source: &str) -> Self {
+        Self {
+            source_name: source_name.to_string(),
+            source: source.to_string(),
+            line_starts: std::iter::once(0)
+                .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+                .collect(),
+            lines: vec![],
+        }
+    }
+
+    /// Map the generated position to byte offset `offset` of the source
+    pub fn add_mapping(&mut self, generated_line: usize, generated_column: usize, offset: usize) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let column = self
+            .source
+            .get(self.line_starts[line]..offset)
+            .map_or(0, |text| text.encode_utf16().count());
+        if self.lines.len() <= generated_line {
+            self.lines.resize(generated_line + 1, vec![]);
+        }
+        self.lines[generated_line].push(Segment {
+            generated_column: generated_column as i64,
+            original: Some(Original {
+                source: 0,
+                line: line as i64,
+                column: column as i64,
+                name: None,
+            }),
+        });
+    }
+
+    pub fn build(self, file: Option<&str>) -> SourceMap {
+        SourceMap {
+            version: 3,
+            file: file.map(|file| file.to_string()),
+            sources: vec![self.source_name],
+            sources_content: Some(vec![Some(self.source)]),
+            names: vec![],
+            mappings: encode(&self.lines),
+        }
+    }
+}
+
+fn encode(lines: &[Vec<Segment>]) -> String {
+    let mut mappings = String::new();
+    let (mut source, mut line, mut column, mut name) = (0, 0, 0, 0);
+    for (i, segments) in lines.iter().enumerate() {
+        if i > 0 {
+            mappings.push(';');
+        }
+        let mut generated_column = 0;
+        for (j, segment) in segments.iter().enumerate() {
+            if j > 0 {
+                mappings.push(',');
+            }
+            encode_vlq(&mut mappings, segment.generated_column - generated_column);
+            generated_column = segment.generated_column;
+            if let Some(original) = segment.original {
+                encode_vlq(&mut mappings, original.source - source);
+                encode_vlq(&mut mappings, original.line - line);
+                encode_vlq(&mut mappings, original.column - column);
+                source = original.source;
+                line = original.line;
+                column = original.column;
+                if let Some(original_name) = original.name {
+                    encode_vlq(&mut mappings, original_name - name);
+                    name = original_name;
+                }
+            }
+        }
+    }
+    mappings
+}
+
+fn encode_vlq(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = vlq & 31;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 32;
+        }
+        out.push(BASE64_CHARS[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+fn decode(mappings: &str) -> Result<Vec<Vec<Segment>>, Px2RemError> {
+    let (mut source, mut line, mut column, mut name) = (0, 0, 0, 0);
+    let mut lines = vec![];
+    for text in mappings.split(';') {
+        let mut segments = vec![];
+        let mut generated_column = 0;
+        for text in text.split(',').filter(|text| !text.is_empty()) {
+            let fields = decode_vlq(text)?;
+            generated_column += fields[0];
+            let original = match fields.len() {
+                1 => None,
+                4 | 5 => {
+                    source += fields[1];
+                    line += fields[2];
+                    column += fields[3];
+                    Some(Original {
+                        source,
+                        line,
+                        column,
+                        name: fields.get(4).map(|delta| {
+                            name += delta;
+                            name
+                        }),
+                    })
+                }
+                _ => {
+                    return Err(Px2RemError::InvalidSourceMap(format!(
+                        "segment `{}` has {} fields",
+                        text,
+                        fields.len()
+                    )))
+                }
+            };
+            segments.push(Segment {
+                generated_column,
+                original,
+            });
+        }
+        lines.push(segments);
+    }
+    Ok(lines)
+}
+
+fn decode_vlq(text: &str) -> Result<Vec<i64>, Px2RemError> {
+    let mut fields = vec![];
+    let (mut value, mut shift) = (0i64, 0);
+    for c in text.bytes() {
+        let digit = BASE64_CHARS.iter().position(|&b| b == c).ok_or_else(|| {
+            Px2RemError::InvalidSourceMap(format!("invalid character `{}`", c as char))
+        })? as i64;
+        // values are 32 bits, a longer one is malformed and would overflow the i64
+        if shift > 30 {
+            return Err(Px2RemError::InvalidSourceMap(format!(
+                "segment `{}` overflows",
+                text
+            )));
+        }
+        value += (digit & 31) << shift;
+        if digit & 32 == 0 {
+            fields.push(if value & 1 == 1 {
+                -(value >> 1)
+            } else {
+                value >> 1
+            });
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 5;
+        }
+    }
+    if shift > 0 {
+        return Err(Px2RemError::InvalidSourceMap(format!(
+            "segment `{}` ends in the middle of a value",
+            text
+        )));
+    }
+    Ok(fields)
+}
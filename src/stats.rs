@@ -0,0 +1,50 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// What the transform did with the stylesheets it visited, see [`crate::transform::Px2Rem::stats`]
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    /// declarations visited, including the ones under a blacklisted selector
+    pub declarations: usize,
+    /// values converted to the target unit
+    pub converted: usize,
+    pub skipped: Skipped,
+    /// values converted per property
+    pub properties: BTreeMap<String, usize>,
+}
+
+/// Values left in the source unit, by reason
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Skipped {
+    pub not_in_prop_list: usize,
+    pub below_min_pixel_value: usize,
+    pub blacklisted_selector: usize,
+    /// outside of `selector_white_list`
+    pub not_in_selector_white_list: usize,
+    /// e.g. `16PX`, which is left alone to opt out of the conversion
+    pub uppercase: usize,
+    /// inside `url()`, `var()` or quotes
+    pub url_var_or_string: usize,
+    /// disabled by a comment directive
+    pub disabled: usize,
+}
+
+impl Stats {
+    /// Add up the counters of `other`, e.g. to total the stats of several files
+    pub fn merge(&mut self, other: &Stats) {
+        self.declarations += other.declarations;
+        self.converted += other.converted;
+        self.skipped.not_in_prop_list += other.skipped.not_in_prop_list;
+        self.skipped.below_min_pixel_value += other.skipped.below_min_pixel_value;
+        self.skipped.blacklisted_selector += other.skipped.blacklisted_selector;
+        self.skipped.not_in_selector_white_list += other.skipped.not_in_selector_white_list;
+        self.skipped.uppercase += other.skipped.uppercase;
+        self.skipped.url_var_or_string += other.skipped.url_var_or_string;
+        self.skipped.disabled += other.skipped.disabled;
+        for (prop, count) in other.properties.iter() {
+            *self.properties.entry(prop.clone()).or_default() += count;
+        }
+    }
+}
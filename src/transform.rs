@@ -1,12 +1,32 @@
+use crate::diff::{apply_edits, Edit};
+use crate::directive::Directives;
+use crate::error::Px2RemError;
 use crate::filter_prop_list::{
     contain, ends_with, exact, not_contain, not_ends_with, not_exact, not_starts_with, starts_with,
+    PropMatcher,
 };
+use crate::lint::Violation;
+use crate::marco_utils::delegate_px2rem;
 use crate::regex;
+use crate::selector::{resolve_nested, split_list};
+use crate::source_map::{SourceMap, SourceMapBuilder};
+use crate::stats::{Skipped, Stats};
+use aho_corasick::AhoCorasick;
+use once_cell::sync::Lazy;
 use recursive_parser::parser::{AtRule, Declaration, Root, Rule, RuleOrAtRuleOrDecl};
 use recursive_parser::visitor::VisitMut;
-use regex::{Captures, Regex};
+use regex::{Captures, Regex, RegexSet};
+use serde::{Deserialize, Deserializer};
 use smol_str::SmolStr;
-use std::{borrow::Cow, fmt::Debug, io::Write, rc::Rc};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Debug},
+    io::Write,
+    str::FromStr,
+    sync::Arc,
+};
 
 #[derive(Debug)]
 pub enum StringOrRegexp {
@@ -14,32 +34,497 @@ pub enum StringOrRegexp {
     String(String),
 }
 
-#[derive(Default)]
+impl StringOrRegexp {
+    /// Convert a postcss-pxtorem `selectorBlackList` entry, a JavaScript regex literal such as
+    /// `/^body$/i` becomes a `Regexp`, anything else such as `/path/to` is a `String`
+    pub fn from_js(value: &str) -> Result<Self, Px2RemError> {
+        let (pattern, flags) = match value
+            .strip_prefix('/')
+            .and_then(|literal| literal.rsplit_once('/'))
+        {
+            Some((pattern, flags)) if is_js_flags(flags) => (pattern, flags),
+            _ => return Ok(StringOrRegexp::String(value.to_string())),
+        };
+        let invalid = |reason: String| Px2RemError::InvalidJsRegexp {
+            pattern: value.to_string(),
+            reason,
+        };
+        let mut inline_flags = String::new();
+        for flag in flags.chars() {
+            match flag {
+                'i' | 'm' | 's' => inline_flags.push(flag),
+                // rust regexps are unicode aware by default, `g` means nothing for a single match
+                'u' | 'g' => {}
+                _ => return Err(invalid(format!("flag `{}` is not supported", flag))),
+            }
+        }
+        let mut re = if inline_flags.is_empty() {
+            String::with_capacity(pattern.len())
+        } else {
+            format!("(?{})", inline_flags)
+        };
+        re.push_str(&js_pattern_to_rust(pattern).map_err(|reason| invalid(reason.to_string()))?);
+        Regex::new(&re).map_err(|err| invalid(err.to_string()))?;
+        Ok(StringOrRegexp::Regexp(re))
+    }
+}
+
+/// Whether `flags` could end a JavaScript regex literal, every flag at most once
+fn is_js_flags(flags: &str) -> bool {
+    flags
+        .char_indices()
+        .all(|(i, flag)| "dgimsuvy".contains(flag) && !flags[..i].contains(flag))
+}
+
+/// Rewrite the JavaScript only spellings, `\/` and `(?<name>`, and reject
+/// the syntax the regex crate has no equivalent for
+fn js_pattern_to_rust(pattern: &str) -> Result<String, &'static str> {
+    let mut re = String::with_capacity(pattern.len());
+    let mut in_class = false;
+    let mut chars = pattern.char_indices();
+    while let Some((i, c)) = chars.next() {
+        let rest = &pattern[i..];
+        match c {
+            '\\' => match chars.next() {
+                Some((_, '1'..='9')) if !in_class => {
+                    return Err("backreferences are not supported")
+                }
+                Some((_, 'k')) if !in_class && rest[2..].starts_with('<') => {
+                    return Err("backreferences are not supported")
+                }
+                Some((_, '/')) => re.push('/'),
+                Some((_, escaped)) => {
+                    re.push('\\');
+                    re.push(escaped);
+                }
+                None => re.push('\\'),
+            },
+            '[' if !in_class => {
+                in_class = true;
+                re.push(c);
+            }
+            ']' if in_class => {
+                in_class = false;
+                re.push(c);
+            }
+            '(' if !in_class && (rest.starts_with("(?<=") || rest.starts_with("(?<!")) => {
+                return Err("lookbehind is not supported")
+            }
+            '(' if !in_class && (rest.starts_with("(?=") || rest.starts_with("(?!")) => {
+                return Err("lookahead is not supported")
+            }
+            '(' if !in_class && rest.starts_with("(?<") => {
+                re.push_str("(?P<");
+                chars.nth(1);
+            }
+            _ => re.push(c),
+        }
+    }
+    Ok(re)
+}
+
+/// config files can only hold strings, JavaScript regex literals such as `/^body$/`
+/// become regexps and the rest are matched as substrings
+impl<'de> Deserialize<'de> for StringOrRegexp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        StringOrRegexp::from_js(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Files whose path matches are left untouched, e.g. anything under `node_modules`
+pub enum Exclude {
+    /// matches the paths containing it, as a string `exclude` of postcss-pxtorem
+    String(String),
+    Regexp(String),
+    Glob(String),
+    Function(Box<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+/// The `exclude` of a config file, see [`Exclude`]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExcludeConfig {
+    String(String),
+    Glob { glob: String },
+}
+
+/// A string is read like postcss-pxtorem does, a JavaScript regex literal such as
+/// `/node_modules/i` is a `Regexp` and anything else a `String`,
+/// a glob is written as `{ "glob": "**/node_modules/**" }`
+impl<'de> Deserialize<'de> for Exclude {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match ExcludeConfig::deserialize(deserializer)? {
+            ExcludeConfig::String(value) => match StringOrRegexp::from_js(&value) {
+                Ok(StringOrRegexp::Regexp(re)) => Ok(Exclude::Regexp(re)),
+                Ok(StringOrRegexp::String(string)) => Ok(Exclude::String(string)),
+                Err(err) => Err(serde::de::Error::custom(err)),
+            },
+            ExcludeConfig::Glob { glob } => Ok(Exclude::Glob(glob)),
+        }
+    }
+}
+
+impl Debug for Exclude {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exclude::String(string) => f.debug_tuple("String").field(string).finish(),
+            Exclude::Regexp(re) => f.debug_tuple("Regexp").field(re).finish(),
+            Exclude::Glob(glob) => f.debug_tuple("Glob").field(glob).finish(),
+            Exclude::Function(_) => f.write_str("Function"),
+        }
+    }
+}
+
+/// compiled form of [`Exclude`]
+enum ExcludeMatcher {
+    String(String),
+    Regexp(Regex),
+    Glob(glob::Pattern),
+    Function(Box<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl ExcludeMatcher {
+    fn new(exclude: Exclude) -> Result<Self, Px2RemError> {
+        Ok(match exclude {
+            Exclude::String(string) => ExcludeMatcher::String(string),
+            Exclude::Regexp(pattern) => match Regex::new(&pattern) {
+                Ok(re) => ExcludeMatcher::Regexp(re),
+                Err(source) => {
+                    return Err(Px2RemError::InvalidExcludeRegexp { pattern, source });
+                }
+            },
+            Exclude::Glob(pattern) => match glob::Pattern::new(&pattern) {
+                Ok(glob) => ExcludeMatcher::Glob(glob),
+                Err(source) => return Err(Px2RemError::InvalidExcludeGlob { pattern, source }),
+            },
+            Exclude::Function(func) => ExcludeMatcher::Function(func),
+        })
+    }
+
+    fn is_match(&self, path: &str) -> bool {
+        match self {
+            ExcludeMatcher::String(string) => path.contains(string.as_str()),
+            ExcludeMatcher::Regexp(re) => re.is_match(path),
+            ExcludeMatcher::Glob(glob) => glob.matches(path),
+            ExcludeMatcher::Function(func) => func(path),
+        }
+    }
+}
+
+impl Debug for ExcludeMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExcludeMatcher::String(string) => f.debug_tuple("String").field(string).finish(),
+            ExcludeMatcher::Regexp(re) => f.debug_tuple("Regexp").field(re).finish(),
+            ExcludeMatcher::Glob(glob) => f.debug_tuple("Glob").field(glob).finish(),
+            ExcludeMatcher::Function(_) => f.write_str("Function"),
+        }
+    }
+}
+
+/// `selector_black_list` or `selector_white_list` compiled once,
+/// the regexps into a set and the strings into an automaton
+#[derive(Debug)]
+struct SelectorMatcher {
+    regexps: RegexSet,
+    strings: AhoCorasick,
+}
+
+/// The error of an invalid regexp, one per list
+type InvalidSelectorList = fn(String, regex::Error) -> Px2RemError;
+
+impl SelectorMatcher {
+    fn new(patterns: &[StringOrRegexp], invalid: InvalidSelectorList) -> Result<Self, Px2RemError> {
+        let mut regexps = vec![];
+        let mut strings = vec![];
+        for pattern in patterns.iter() {
+            match pattern {
+                StringOrRegexp::Regexp(pattern) => {
+                    if let Err(source) = Regex::new(pattern) {
+                        return Err(invalid(pattern.to_string(), source));
+                    }
+                    regexps.push(pattern.as_str());
+                }
+                StringOrRegexp::String(string) => strings.push(string.as_str()),
+            }
+        }
+        // every regexp compiles on its own, the set can still exceed the size limit
+        let regexps =
+            RegexSet::new(&regexps).map_err(|source| invalid(regexps.join("|"), source))?;
+        Ok(Self {
+            regexps,
+            strings: AhoCorasick::new(&strings),
+        })
+    }
+
+    fn is_match(&self, selector: &str) -> bool {
+        self.regexps.is_match(selector) || self.strings.is_match(selector)
+    }
+
+    /// Like [`SelectorMatcher::is_match`], but a string only matches whole names,
+    /// e.g. `.ignore` matches `.ignore.a` and `div .ignore` but not `.ignore-me`
+    fn is_match_on_boundaries(&self, selector: &str) -> bool {
+        self.regexps.is_match(selector)
+            || self.strings.find_overlapping_iter(selector).any(|m| {
+                let pattern = &selector[m.start()..m.end()];
+                // an element name must not continue a class or id either
+                let cut_before = pattern.starts_with(is_name_char)
+                    && matches!(selector[..m.start()].chars().next_back(),
+                        Some(c) if is_name_char(c) || c == '.' || c == '#');
+                let cut_after = pattern.ends_with(is_name_char)
+                    && matches!(selector[m.end()..].chars().next(), Some(c) if is_name_char(c));
+                !cut_before && !cut_after
+            })
+    }
+}
+
+/// Whether `c` may be part of a class, id or element name
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+/// Picks the root value from the path of the stylesheet,
+/// e.g. `37.5` for the mobile site and `16` for everything else
+pub type RootValueResolver = Box<dyn Fn(&str) -> f64 + Send + Sync>;
+
+/// Deserializable with the postcss-pxtorem option names, e.g. `rootValue`, `propList`
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct Px2RemOption {
-    pub root_value: Option<i32>,
+    pub root_value: Option<f64>,
+    /// takes precedence over `root_value` when the path of the stylesheet is known
+    #[serde(skip)]
+    pub root_value_resolver: Option<RootValueResolver>,
     pub unit_precision: Option<i32>,
     pub selector_black_list: Option<Vec<StringOrRegexp>>,
+    /// when not empty, only the rules matching one of these are converted, matched like
+    /// `selector_black_list` except that a string ending in ` *` such as `.mobile-root *`
+    /// matches the descendants and children of it. What is not under such a rule is left
+    /// alone, e.g. `@font-face` and the params of the at-rules outside of them
+    pub selector_white_list: Option<Vec<StringOrRegexp>>,
+    /// match `selector_black_list` and `selector_white_list` against every selector of a list
+    /// such as `.a, .b`, strings on class, id and element boundaries, and split the rules
+    /// only partly converted
+    pub split_selector_list: Option<bool>,
     pub prop_list: Option<Vec<String>>,
     pub replace: Option<bool>,
+    /// shorthand adding `media` to `at_rule_list`, as in postcss-pxtorem
     pub media_query: Option<bool>,
+    /// names of the at-rules whose params are converted too, e.g. `container`, `supports`
+    /// or `custom-media`, matched ignoring case, none by default
+    pub at_rule_list: Option<Vec<String>>,
     pub min_pixel_value: Option<f64>,
+    pub exclude: Option<Exclude>,
 }
 
+struct RootValueFn(RootValueResolver);
+
+impl Debug for RootValueFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RootValueResolver")
+    }
+}
+
+/// Absolute length unit converted by [`UnitConverter`]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceUnit {
+    Px,
+    Pt,
+    Pc,
+    In,
+    Cm,
+    Mm,
+    #[serde(rename = "Q")]
+    Q,
+}
+
+impl SourceUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceUnit::Px => "px",
+            SourceUnit::Pt => "pt",
+            SourceUnit::Pc => "pc",
+            SourceUnit::In => "in",
+            SourceUnit::Cm => "cm",
+            SourceUnit::Mm => "mm",
+            SourceUnit::Q => "Q",
+        }
+    }
+
+    /// How many px one unit is, as defined by CSS Values and Units
+    pub fn px_ratio(&self) -> f64 {
+        match self {
+            SourceUnit::Px => 1f64,
+            SourceUnit::Pt => 96f64 / 72f64,
+            SourceUnit::Pc => 16f64,
+            SourceUnit::In => 96f64,
+            SourceUnit::Cm => 96f64 / 2.54,
+            SourceUnit::Mm => 96f64 / 25.4,
+            SourceUnit::Q => 96f64 / 101.6,
+        }
+    }
+}
+
+impl FromStr for SourceUnit {
+    type Err = String;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match unit {
+            "px" => Ok(SourceUnit::Px),
+            "pt" => Ok(SourceUnit::Pt),
+            "pc" => Ok(SourceUnit::Pc),
+            "in" => Ok(SourceUnit::In),
+            "cm" => Ok(SourceUnit::Cm),
+            "mm" => Ok(SourceUnit::Mm),
+            "Q" => Ok(SourceUnit::Q),
+            _ => Err(format!("unknown source unit `{}`", unit)),
+        }
+    }
+}
+
+/// Unit produced by [`UnitConverter`]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetUnit {
+    Rem,
+    Em,
+    Vw,
+    Vh,
+    Vmin,
+    Vmax,
+    Rpx,
+    Px,
+}
+
+impl TargetUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TargetUnit::Rem => "rem",
+            TargetUnit::Em => "em",
+            TargetUnit::Vw => "vw",
+            TargetUnit::Vh => "vh",
+            TargetUnit::Vmin => "vmin",
+            TargetUnit::Vmax => "vmax",
+            TargetUnit::Rpx => "rpx",
+            TargetUnit::Px => "px",
+        }
+    }
+}
+
+impl FromStr for TargetUnit {
+    type Err = String;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match unit {
+            "rem" => Ok(TargetUnit::Rem),
+            "em" => Ok(TargetUnit::Em),
+            "vw" => Ok(TargetUnit::Vw),
+            "vh" => Ok(TargetUnit::Vh),
+            "vmin" => Ok(TargetUnit::Vmin),
+            "vmax" => Ok(TargetUnit::Vmax),
+            "rpx" => Ok(TargetUnit::Rpx),
+            "px" => Ok(TargetUnit::Px),
+            _ => Err(format!("unknown target unit `{}`", unit)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Convert(SourceUnit, TargetUnit),
+    Rem2Px,
+}
+
+impl Direction {
+    /// The unit converted from
+    fn source_unit(&self) -> &'static str {
+        match self {
+            Direction::Convert(from, _) => from.as_str(),
+            Direction::Rem2Px => "rem",
+        }
+    }
+}
+
+/// Cheap to clone, the compiled configuration is shared between the clones,
+/// e.g. one per thread when converting many stylesheets in parallel.
+/// The stats and the prop cache are not, every clone starts without them
 #[derive(Debug)]
 pub struct Px2Rem {
-    px_regex: &'static Regex,
-    root_value: i32,
+    direction: Direction,
+    root_value: f64,
+    /// width of the design draft, the base of the viewport units and `rpx`
+    viewport_width: f64,
+    /// height of the design draft, the base of `vh`
+    viewport_height: f64,
+    /// inside `@media (orientation: landscape)` px become `vw` of this width
+    landscape_width: Option<f64>,
+    root_value_resolver: Option<Arc<RootValueFn>>,
+    /// root value resolved for `from`
+    file_root_value: Option<f64>,
     unit_precision: i32,
-    selector_black_list: Vec<StringOrRegexp>,
-    prop_list: Rc<Vec<String>>,
+    selector_black_list: Option<Arc<SelectorMatcher>>,
+    selector_white_list: Option<Arc<SelectorMatcher>>,
+    split_selector_list: bool,
+    prop_list: Arc<Vec<String>>,
     replace: bool,
-    media_query: bool,
+    /// names of the at-rules whose params are converted
+    at_rule_list: Arc<Vec<String>>,
     min_pixel_value: f64,
-    has_wild: bool, // exclude: null we don't need the prop, since this is always used for cli
-    pub match_list: MatchList,
+    exclude: Option<Arc<ExcludeMatcher>>,
+    /// path of the stylesheet being visited, checked against `exclude`
+    from: Option<String>,
+    /// `prop_list` sorted by kind of pattern, see [`Px2Rem::generate_match_list`]
+    match_list: MatchList,
     // exact_list: Vec<&'a String>,
-    all_match: bool,
+    /// `match_list` compiled, shared between the clones
+    prop_matcher: Arc<PropMatcher>,
+    /// whether each prop seen so far is matched, stylesheets repeat the same few props
+    prop_cache: RefCell<HashMap<SmolStr, bool>>,
     map_stack: Vec<Vec<(SmolStr, SmolStr)>>,
+    /// resolved selectors of the rules being visited, used to resolve nested rules
+    selector_stack: Vec<String>,
+    /// comment directives of the stylesheet being visited
+    directives: Directives,
+    /// how many `@media (orientation: landscape)` enclose the node being visited
+    landscape_depth: usize,
+    stats: RefCell<Stats>,
+    /// declarations and at-rule params that would be converted, with their offsets, while linting
+    lint: Option<Vec<(usize, String, String, String)>>,
+}
+
+impl Clone for Px2Rem {
+    fn clone(&self) -> Self {
+        Self {
+            direction: self.direction,
+            root_value: self.root_value,
+            viewport_width: self.viewport_width,
+            viewport_height: self.viewport_height,
+            landscape_width: self.landscape_width,
+            root_value_resolver: self.root_value_resolver.clone(),
+            file_root_value: self.file_root_value,
+            unit_precision: self.unit_precision,
+            selector_black_list: self.selector_black_list.clone(),
+            selector_white_list: self.selector_white_list.clone(),
+            split_selector_list: self.split_selector_list,
+            prop_list: self.prop_list.clone(),
+            replace: self.replace,
+            at_rule_list: self.at_rule_list.clone(),
+            min_pixel_value: self.min_pixel_value,
+            exclude: self.exclude.clone(),
+            from: self.from.clone(),
+            match_list: self.match_list.clone(),
+            prop_matcher: self.prop_matcher.clone(),
+            prop_cache: RefCell::default(),
+            map_stack: self.map_stack.clone(),
+            selector_stack: self.selector_stack.clone(),
+            directives: self.directives.clone(),
+            landscape_depth: self.landscape_depth,
+            stats: RefCell::default(),
+            lint: self.lint.clone(),
+        }
+    }
 }
 
 impl Default for Px2Rem {
@@ -51,23 +536,37 @@ impl Default for Px2Rem {
     fn default() -> Px2Rem {
         // let prop_list = ;
         let ret = Self {
-            px_regex: regex!(r#""[^"]+"|'[^']+'|url\([^)]+\)|var\([^)]+\)|(\d*\.?\d+)px"#),
-            root_value: 16,
+            direction: Direction::Convert(SourceUnit::Px, TargetUnit::Rem),
+            root_value: 16f64,
+            viewport_width: DEFAULT_VIEWPORT_WIDTH,
+            viewport_height: DEFAULT_VIEWPORT_HEIGHT,
+            landscape_width: None,
+            root_value_resolver: None,
+            file_root_value: None,
             unit_precision: 5,
-            selector_black_list: vec![],
-            prop_list: Rc::new(vec![
+            selector_black_list: None,
+            selector_white_list: None,
+            split_selector_list: false,
+            prop_list: Arc::new(vec![
                 "font".to_string(),
                 "font-size".to_string(),
                 "line-height".to_string(),
                 "letter-spacing".to_string(),
             ]),
             replace: true,
-            media_query: false,
+            at_rule_list: Arc::new(vec![]),
             min_pixel_value: 0f64,
-            has_wild: false,
+            exclude: None,
+            from: None,
             match_list: MatchList::default(),
-            all_match: false,
+            prop_matcher: Arc::new(PropMatcher::new(&MatchList::default(), false, false)),
+            prop_cache: RefCell::default(),
             map_stack: vec![],
+            selector_stack: vec![],
+            directives: Directives::default(),
+            landscape_depth: 0,
+            stats: RefCell::default(),
+            lint: None,
         };
         // ret.generate_match_list();
         ret
@@ -75,32 +574,103 @@ impl Default for Px2Rem {
 }
 
 impl Px2Rem {
-    /// new constructor will automatically generate match list for you
+    /// new constructor will automatically generate match list for you,
+    /// panics if the option is invalid, see [`Px2Rem::try_new`]
     pub fn new(option: Px2RemOption) -> Self {
+        match Self::try_new(option) {
+            Ok(ret) => ret,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Like [`Px2Rem::new`], but reports invalid regexps and globs in the option
+    pub fn try_new(option: Px2RemOption) -> Result<Self, Px2RemError> {
         let mut ret = Self::default();
         if let Some(root_value) = option.root_value {
             ret.root_value = root_value;
         }
+        ret.root_value_resolver = option
+            .root_value_resolver
+            .map(|resolver| Arc::new(RootValueFn(resolver)));
         if let Some(unit_precision) = option.unit_precision {
             ret.unit_precision = unit_precision;
         }
         if let Some(selector_black_list) = option.selector_black_list {
-            ret.selector_black_list = selector_black_list;
+            if !selector_black_list.is_empty() {
+                ret.selector_black_list = Some(Arc::new(SelectorMatcher::new(
+                    &selector_black_list,
+                    |pattern, source| Px2RemError::InvalidSelectorBlackList { pattern, source },
+                )?));
+            }
+        }
+        if let Some(selector_white_list) = option.selector_white_list {
+            if !selector_white_list.is_empty() {
+                let selector_white_list = selector_white_list
+                    .into_iter()
+                    .map(|pattern| match pattern {
+                        StringOrRegexp::String(string) if string.ends_with(" *") => {
+                            // the descendants and children of the scope, not its siblings
+                            StringOrRegexp::Regexp(format!(
+                                r"{}(?:\s*>\s*|\s+)[^\s>+~]",
+                                regex::escape(string[..string.len() - 2].trim_end())
+                            ))
+                        }
+                        pattern => pattern,
+                    })
+                    .collect::<Vec<_>>();
+                ret.selector_white_list = Some(Arc::new(SelectorMatcher::new(
+                    &selector_white_list,
+                    |pattern, source| Px2RemError::InvalidSelectorWhiteList { pattern, source },
+                )?));
+            }
+        }
+        if let Some(split_selector_list) = option.split_selector_list {
+            ret.split_selector_list = split_selector_list;
         }
         if let Some(prop_list) = option.prop_list {
-            ret.prop_list = Rc::new(prop_list);
+            ret.prop_list = Arc::new(prop_list);
         }
         if let Some(replace) = option.replace {
             ret.replace = replace;
         }
-        if let Some(media_query) = option.media_query {
-            ret.media_query = media_query;
+        let mut at_rule_list = option.at_rule_list.unwrap_or_default();
+        if option.media_query == Some(true)
+            && !at_rule_list
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case("media"))
+        {
+            at_rule_list.push("media".to_string());
         }
+        ret.at_rule_list = Arc::new(at_rule_list);
         if let Some(min_pixel_value) = option.min_pixel_value {
             ret.min_pixel_value = min_pixel_value;
         }
+        if let Some(exclude) = option.exclude {
+            ret.exclude = Some(Arc::new(ExcludeMatcher::new(exclude)?));
+        }
         ret.generate_match_list();
-        ret
+        Ok(ret)
+    }
+
+    /// Fallible version of `visit_root`, fails on declarations outside of any rule
+    /// instead of leaving them untouched
+    pub fn try_visit_root(&mut self, root: &mut Root) -> Result<(), Px2RemError> {
+        for child in root.children.iter() {
+            if let RuleOrAtRuleOrDecl::Declaration(decl) = child {
+                self.directives = Directives::default();
+                return Err(Px2RemError::TopLevelDeclaration {
+                    prop: decl.prop.to_string(),
+                    value: decl.value.to_string(),
+                });
+            }
+        }
+        self.visit_root(root);
+        Ok(())
+    }
+
+    /// The patterns of `prop_list` sorted by kind, filled in by [`Px2Rem::generate_match_list`]
+    pub fn match_list(&self) -> &MatchList {
+        &self.match_list
     }
 
     pub fn generate_match_list(&mut self) {
@@ -118,130 +688,561 @@ impl Px2Rem {
         };
         let has_wild = self.prop_list.iter().any(|prop| prop == "*");
         let match_all = has_wild && self.prop_list.len() == 1;
-        self.has_wild = has_wild;
-        self.all_match = match_all;
+        self.prop_matcher = Arc::new(PropMatcher::new(&self.match_list, has_wild, match_all));
+        self.prop_cache.get_mut().clear();
+    }
+
+    /// Set the path of the stylesheet that is visited next,
+    /// `visit_root` leaves the whole stylesheet untouched if the path is excluded,
+    /// and `root_value_resolver` picks the root value from it
+    pub fn set_from(&mut self, from: Option<&str>) {
+        self.from = from.map(|from| from.to_string());
+        self.file_root_value = match (&self.root_value_resolver, from) {
+            (Some(resolver), Some(from)) => Some((resolver.0)(from)),
+            _ => None,
+        };
+    }
+
+    /// Report every declaration and at-rule params of `root` that would be converted instead of
+    /// converting them, `source` is the text `root` was parsed from and directives in it are honored
+    pub fn lint(&mut self, root: &mut Root, source: &str) -> Vec<Violation> {
+        self.set_source(source);
+        self.lint = Some(vec![]);
+        self.visit_root(root);
+        self.lint
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(offset, prop, value, suggested)| {
+                Violation::at(source, offset, prop, value, suggested)
+            })
+            .collect()
+    }
+
+    fn lint_declaration(&mut self, decl: &Declaration) {
+        if let Some(suggested) = self.converted_value(decl) {
+            if let Some(lint) = self.lint.as_mut() {
+                lint.push((
+                    decl.start,
+                    decl.prop.to_string(),
+                    decl.value.to_string(),
+                    suggested,
+                ));
+            }
+        }
+    }
+
+    /// Convert the params of an at-rule in `at_rule_list`, or report them while linting
+    fn convert_params(&mut self, at_rule: &mut AtRule) {
+        let mut counts = ValueCounts::default();
+        let value = self
+            .replace_value(&at_rule.params, &mut counts)
+            .into_owned();
+        self.add_counts(&counts);
+        if value != at_rule.params {
+            match self.lint.as_mut() {
+                Some(lint) => lint.push((
+                    at_rule.start,
+                    format!("@{}", at_rule.name),
+                    at_rule.params.to_string(),
+                    value,
+                )),
+                None => at_rule.params = Cow::Owned(value),
+            }
+        }
+    }
+
+    /// Set the source text of the stylesheet that is visited next, comment directives such as
+    /// `/* px2rem-disable-next-line */` are only honored when the source is known.
+    /// They are dropped once the root is visited, so set it again for every stylesheet
+    pub fn set_source(&mut self, source: &str) {
+        self.directives = Directives::parse(source);
+    }
+
+    /// The root value used for the stylesheet being visited
+    pub fn root_value(&self) -> f64 {
+        self.file_root_value.unwrap_or(self.root_value)
+    }
+
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(path))
     }
 
     pub fn px_replace<'a>(&self, value: &'a str) -> Cow<'a, str> {
-        self.px_regex.replace_all(value, |caps: &Captures| {
-            let pixels_value = &caps.get(1);
-            if let Some(pixels_value) = pixels_value {
-                match pixels_value.as_str().parse::<f64>() {
-                    Ok(pixels) => {
-                        if pixels < self.min_pixel_value {
-                            return caps[0].to_string();
-                        }
-                        let fixed_value = pixels / self.root_value as f64;
-                        if fixed_value == 0f64 {
-                            caps[0].to_string()
-                        } else {
-                            let mut res =
-                                format!("{:.*}", self.unit_precision as usize, fixed_value);
-                            let cont = res.ends_with('0');
-                            if cont {
-                                let mut temp = res.trim_end_matches('0');
-                                if temp.ends_with('.') {
-                                    temp = &temp[0..temp.len() - 1];
-                                }
-                                res = temp.to_string();
-                            }
-                            res + "rem"
-                        }
+        let direction = Direction::Convert(SourceUnit::Px, TargetUnit::Rem);
+        self.convert(value, direction, &mut ValueCounts::default())
+    }
+
+    /// The reverse of `px_replace`, `min_pixel_value` applies to the resulting pixels
+    pub fn rem_replace<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        self.convert(value, Direction::Rem2Px, &mut ValueCounts::default())
+    }
+
+    /// Converts every value in the source unit of `direction` through its size in px,
+    /// `min_pixel_value` applies to that size and zero is left as it is
+    fn convert<'a>(
+        &self,
+        value: &'a str,
+        direction: Direction,
+        counts: &mut ValueCounts,
+    ) -> Cow<'a, str> {
+        let (source_pixels, target_pixels, target) = self.unit_sizes(direction);
+        unit_regex(direction.source_unit()).replace_all(value, |caps: &Captures| {
+            match caps
+                .get(1)
+                .and_then(|number| number.as_str().parse::<f64>().ok())
+            {
+                Some(number) => {
+                    let pixels = number * source_pixels;
+                    if pixels == 0f64 {
+                        caps[0].to_string()
+                    } else if pixels < self.min_pixel_value {
+                        counts.below_min_pixel_value += 1;
+                        caps[0].to_string()
+                    } else {
+                        counts.converted += 1;
+                        to_fixed(pixels / target_pixels, self.unit_precision) + target
                     }
-                    Err(_) => caps[0].to_string(),
                 }
-            } else {
-                caps[0].to_string()
+                None => {
+                    counts.url_var_or_string += self.count_quoted(&caps[0]);
+                    caps[0].to_string()
+                }
             }
         })
     }
 
-    #[allow(non_snake_case)]
-    pub fn blacklisted_selector(&self, selector: &str) -> bool {
-        if self.selector_black_list.is_empty() {
-            return false;
-        }
-        let BLACK_LIST_RE: once_cell::sync::OnceCell<regex::Regex> =
-            once_cell::sync::OnceCell::new();
-        // TODO: this implementation is inefficient
-        let re = BLACK_LIST_RE.get_or_init(|| {
-            regex::Regex::new(
-                &self
-                    .selector_black_list
-                    .iter()
-                    .filter(|re| match re {
-                        StringOrRegexp::Regexp(_) => true,
-                        StringOrRegexp::String(_) => false,
-                    })
-                    .map(|reg| match reg {
-                        StringOrRegexp::Regexp(str) => str.to_string(),
-                        StringOrRegexp::String(_) => unreachable!(),
-                    })
-                    .collect::<Vec<_>>()
-                    .join("|"),
-            )
-            .unwrap()
-        });
-        (if re.as_str().is_empty() {
-            false
+    /// The size in px of the source and target units of `direction`, and the target unit,
+    /// inside `@media (orientation: landscape)` with a `landscape_width` it is `vw` of that width
+    fn unit_sizes(&self, direction: Direction) -> (f64, f64, &'static str) {
+        let (from, to) = match direction {
+            Direction::Convert(from, to) => (from, to),
+            Direction::Rem2Px => return (self.root_value(), 1f64, "px"),
+        };
+        let (to, width) = match self.landscape_width {
+            Some(width) if self.landscape_depth > 0 => (TargetUnit::Vw, width),
+            _ => (to, self.viewport_width),
+        };
+        let target_pixels = match to {
+            TargetUnit::Rem | TargetUnit::Em => self.root_value(),
+            TargetUnit::Vw => width / 100f64,
+            TargetUnit::Vh => self.viewport_height / 100f64,
+            TargetUnit::Vmin => width.min(self.viewport_height) / 100f64,
+            TargetUnit::Vmax => width.max(self.viewport_height) / 100f64,
+            TargetUnit::Rpx => width / 750f64,
+            TargetUnit::Px => 1f64,
+        };
+        (from.px_ratio(), target_pixels, to.as_str())
+    }
+
+    /// Number of values in the source unit, not counting the ones in strings, `url()` and `var()`
+    fn count_values(&self, value: &str) -> usize {
+        unit_regex(self.source_unit())
+            .captures_iter(value)
+            .filter(|caps| caps.get(1).is_some())
+            .count()
+    }
+
+    /// Number of values in the source unit inside a matched string, `url()` or `var()`,
+    /// which would have been converted if not quoted
+    fn count_quoted(&self, matched: &str) -> usize {
+        match matched.find(['"', '\'', '(']) {
+            Some(open) => self.count_values(&matched[open + 1..matched.len() - 1]),
+            None => 0,
+        }
+    }
+
+    /// Add the counts of a converted value to the stats
+    fn add_counts(&self, counts: &ValueCounts) {
+        let mut stats = self.stats.borrow_mut();
+        stats.converted += counts.converted;
+        stats.skipped.below_min_pixel_value += counts.below_min_pixel_value;
+        stats.skipped.url_var_or_string += counts.url_var_or_string;
+    }
+
+    /// Count the values of the declarations under a rule or at-rule that is skipped as a whole
+    fn count_skipped(
+        &self,
+        children: &[RuleOrAtRuleOrDecl],
+        skipped: fn(&mut Skipped) -> &mut usize,
+    ) {
+        for child in children.iter() {
+            match child {
+                RuleOrAtRuleOrDecl::Rule(rule) => self.count_skipped(&rule.children, skipped),
+                RuleOrAtRuleOrDecl::AtRule(at_rule) => {
+                    self.count_skipped(&at_rule.children, skipped)
+                }
+                RuleOrAtRuleOrDecl::Declaration(decl) => {
+                    self.count_skipped_declaration(decl, skipped)
+                }
+            }
+        }
+    }
+
+    fn count_skipped_declaration(
+        &self,
+        decl: &Declaration,
+        skipped: fn(&mut Skipped) -> &mut usize,
+    ) {
+        let count = if self.is_match(&decl.prop) {
+            self.count_values(&decl.value)
         } else {
-            re.is_match(selector)
-        }) || {
-            self.selector_black_list
+            0
+        };
+        let mut stats = self.stats.borrow_mut();
+        stats.declarations += 1;
+        *skipped(&mut stats.skipped) += count;
+    }
+
+    /// What has been converted and skipped since the last [`Px2Rem::take_stats`]
+    pub fn stats(&self) -> Stats {
+        self.stats.borrow().clone()
+    }
+
+    /// Like [`Px2Rem::stats`], but resets the counters, e.g. to get the stats of every file
+    pub fn take_stats(&mut self) -> Stats {
+        self.stats.take()
+    }
+
+    /// The unit converted from, depending on the direction
+    fn source_unit(&self) -> &'static str {
+        self.direction.source_unit()
+    }
+
+    /// Convert `value` in the current direction, what is converted and skipped is added to
+    /// `counts` rather than the stats, the caller decides whether the conversion is kept
+    fn replace_value<'a>(&self, value: &'a str, counts: &mut ValueCounts) -> Cow<'a, str> {
+        self.convert(value, self.direction, counts)
+    }
+
+    /// The value of `decl` with px converted to rem, `None` if `decl` should be left as it is,
+    /// e.g. the prop is not in `prop_list` or the same rem declaration already exists
+    fn converted_value(&self, decl: &Declaration) -> Option<String> {
+        let unit = self.source_unit();
+        {
+            let mut stats = self.stats.borrow_mut();
+            stats.declarations += 1;
+            stats.skipped.uppercase += count_uppercase(&decl.value, unit);
+        }
+        if !decl.value.contains(unit) {
+            return None;
+        }
+        if self
+            .directives
+            .is_declaration_disabled(decl.start, decl.end)
+        {
+            self.stats.borrow_mut().skipped.disabled += self.count_values(&decl.value);
+            return None;
+        }
+        if !self.is_match(&decl.prop) {
+            self.stats.borrow_mut().skipped.not_in_prop_list += self.count_values(&decl.value);
+            return None;
+        }
+        let mut counts = ValueCounts::default();
+        let value = self.replace_value(&decl.value, &mut counts);
+        if value == decl.value {
+            self.add_counts(&counts);
+            return None;
+        }
+        if let Some(vec) = self.map_stack.last() {
+            if vec
                 .iter()
-                .any(|pattern| match pattern {
-                    StringOrRegexp::Regexp(_) => false,
-                    StringOrRegexp::String(string) => selector.contains(string),
-                })
+                .any(|(k, v)| k.as_str() == decl.prop && v.as_str() == value)
+            {
+                // the converted declaration already exists, nothing is converted or skipped
+                return None;
+            }
         }
+        self.add_counts(&counts);
+        *self
+            .stats
+            .borrow_mut()
+            .properties
+            .entry(decl.prop.to_string())
+            .or_default() += counts.converted;
+        Some(value.into_owned())
     }
 
-    fn is_match(&self, prop: &str) -> bool {
-        // TODO: this implementation maybe not efficient, need to explore a better way
-        if self.all_match {
-            return true;
+    /// A copy of `decl` with the converted value, inserted after `decl` when `replace` is false
+    fn converted_declaration<'a>(&self, decl: &Declaration<'a>) -> Option<Declaration<'a>> {
+        let value = self.converted_value(decl)?;
+        let mut converted = decl.clone();
+        converted.value = Cow::Owned(value);
+        Some(converted)
+    }
+
+    pub fn blacklisted_selector(&self, selector: &str) -> bool {
+        match &self.selector_black_list {
+            Some(matcher) if self.split_selector_list => split_list(selector)
+                .into_iter()
+                .any(|selector| matcher.is_match_on_boundaries(selector)),
+            Some(matcher) => matcher.is_match(selector),
+            None => false,
+        }
+    }
+
+    /// Whether the values under `selector` may be converted, always true without a
+    /// `selector_white_list`. Otherwise false without a selector, i.e. outside of any rule
+    /// such as in a top level `@font-face` or `@page`, or for the params of a top level at-rule
+    pub fn whitelisted_selector(&self, selector: Option<&str>) -> bool {
+        match (&self.selector_white_list, selector) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(matcher), Some(selector)) if self.split_selector_list => split_list(selector)
+                .into_iter()
+                .any(|selector| matcher.is_match_on_boundaries(selector)),
+            (Some(matcher), Some(selector)) => matcher.is_match(selector),
+        }
+    }
+
+    /// With `split_selector_list`, the selectors of `rule` to keep and the ones that are
+    /// blacklisted or not whitelisted, e.g. `.keep` and `.ignore` for `.keep, .ignore`.
+    /// None when the selectors are all skipped or none is
+    fn split_selectors(&self, rule: &Rule) -> Option<(String, String)> {
+        if !self.split_selector_list
+            || (self.selector_black_list.is_none() && self.selector_white_list.is_none())
+        {
+            return None;
+        }
+        let (skipped, kept): (Vec<&str>, Vec<&str>) = split_list(&rule.selector)
+            .into_iter()
+            .partition(|selector| {
+                let resolved = match self.selector_stack.last() {
+                    Some(parent) => resolve_nested(parent, selector),
+                    None => selector.to_string(),
+                };
+                self.blacklisted_selector(&resolved) || !self.whitelisted_selector(Some(&resolved))
+            });
+        if skipped.is_empty() || kept.is_empty() {
+            return None;
+        }
+        Some((kept.join(", "), skipped.join(", ")))
+    }
+
+    /// Visit `rule`, split with [`Px2Rem::split_selectors`] first, returns the copy with the
+    /// skipped selectors, which is to be inserted after it.
+    /// While linting both parts are checked on copies and `rule` is left as it is
+    fn visit_split_rule<'a>(&mut self, rule: &mut Rule<'a>) -> Option<Rule<'a>> {
+        let (kept, skipped) = match self.split_selectors(rule) {
+            Some(split) => split,
+            None => {
+                self.visit_rule(rule);
+                return None;
+            }
         };
-        return (self.has_wild
-            || self
-                .match_list
-                .exact_list
-                .iter()
-                .any(|p| p.as_str() == prop)
-            || self
-                .match_list
-                .contain_list
-                .iter()
-                .any(|p| prop.contains(p.as_str()))
-            || self
-                .match_list
-                .starts_with_list
-                .iter()
-                .any(|p| prop.starts_with(p.as_str()))
-            || self
-                .match_list
-                .ends_with_list
-                .iter()
-                .any(|p| prop.ends_with(p.as_str())))
-            && !(self.match_list.not_exact_list.iter().any(|p| p == prop)
-                || self
-                    .match_list
-                    .not_contain_list
-                    .iter()
-                    .any(|p| prop.contains(p.as_str()))
-                || self
-                    .match_list
-                    .not_starts_list
-                    .iter()
-                    .any(|p| prop.starts_with(p.as_str()))
-                || self
-                    .match_list
-                    .not_ends_list
-                    .iter()
-                    .any(|p| prop.ends_with(p.as_str())));
-    }
-}
-
-#[derive(Default, Debug)]
+        let mut copy = rule.clone();
+        copy.selector = Cow::Owned(skipped);
+        if self.lint.is_some() {
+            let mut kept_copy = rule.clone();
+            kept_copy.selector = Cow::Owned(kept);
+            self.visit_rule(&mut kept_copy);
+            self.visit_rule(&mut copy);
+            return None;
+        }
+        rule.selector = Cow::Owned(kept);
+        self.visit_rule(rule);
+        Some(copy)
+    }
+
+    fn is_match(&self, prop: &str) -> bool {
+        if let Some(&matched) = self.prop_cache.borrow().get(prop) {
+            return matched;
+        }
+        let matched = self.prop_matcher.is_match(prop);
+        self.prop_cache.borrow_mut().insert(prop.into(), matched);
+        matched
+    }
+}
+
+/// What converting a single value did, added to the [`Stats`] once the conversion is kept
+#[derive(Default)]
+struct ValueCounts {
+    converted: usize,
+    below_min_pixel_value: usize,
+    url_var_or_string: usize,
+}
+
+/// Matches the values in `unit`, and the strings, `url()` and `var()` to skip
+fn unit_regex(unit: &str) -> &'static Regex {
+    static REGEXES: Lazy<HashMap<&'static str, Regex>> = Lazy::new(|| {
+        ["px", "pt", "pc", "in", "cm", "mm", "Q", "rem"]
+            .into_iter()
+            .map(|unit| {
+                let pattern = format!(
+                    r#""[^"]+"|'[^']+'|url\([^)]+\)|var\([^)]+\)|(\d*\.?\d+){}"#,
+                    unit
+                );
+                (unit, Regex::new(&pattern).unwrap())
+            })
+            .collect()
+    });
+    &REGEXES[unit]
+}
+
+/// Number of values in the upper case of `unit` such as `16PX`, which are left as they are
+fn count_uppercase(value: &str, unit: &str) -> usize {
+    let uppercase = unit.to_uppercase();
+    if uppercase == unit {
+        return 0;
+    }
+    value
+        .match_indices(&uppercase)
+        .filter(|(i, _)| value[..*i].ends_with(|c: char| c.is_ascii_digit()))
+        .count()
+}
+
+/// Format `value` with at most `precision` decimals and no trailing zeros
+fn to_fixed(value: f64, precision: i32) -> String {
+    let res = format!("{:.*}", precision as usize, value);
+    if res.contains('.') {
+        res.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        res
+    }
+}
+
+/// Converts rem back to px, e.g. for email templates and renderers ignoring rem,
+/// shares the options of [`Px2Rem`], `1rem` becomes `root_value` px rounded to `unit_precision`
+#[derive(Debug, Clone)]
+pub struct Rem2Px(Px2Rem);
+
+impl Rem2Px {
+    /// panics if the option is invalid, see [`Rem2Px::try_new`]
+    pub fn new(option: Px2RemOption) -> Self {
+        match Self::try_new(option) {
+            Ok(ret) => ret,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub fn try_new(option: Px2RemOption) -> Result<Self, Px2RemError> {
+        let mut px_to_rem = Px2Rem::try_new(option)?;
+        px_to_rem.direction = Direction::Rem2Px;
+        Ok(Self(px_to_rem))
+    }
+}
+
+delegate_px2rem!(Rem2Px);
+
+/// Deserializable like [`Px2RemOption`], with `from`, `to` and `viewportWidth` on top
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UnitConverterOption {
+    /// `px` by default
+    pub from: Option<SourceUnit>,
+    /// `rem` by default
+    pub to: Option<TargetUnit>,
+    /// width of the design draft, the base of `vw` and `rpx`, `320` by default
+    pub viewport_width: Option<f64>,
+    /// height of the design draft, the base of `vh`, `568` by default
+    pub viewport_height: Option<f64>,
+    #[serde(flatten)]
+    pub common: Px2RemOption,
+}
+
+/// Converts any absolute length unit to `rem`, `em`, `vw`, `rpx` or `px`,
+/// [`Px2Rem`] is the `px` to `rem` preset of it
+#[derive(Debug, Clone)]
+pub struct UnitConverter(Px2Rem);
+
+impl UnitConverter {
+    /// panics if the option is invalid, see [`UnitConverter::try_new`]
+    pub fn new(option: UnitConverterOption) -> Self {
+        match Self::try_new(option) {
+            Ok(ret) => ret,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub fn try_new(option: UnitConverterOption) -> Result<Self, Px2RemError> {
+        let mut px_to_rem = Px2Rem::try_new(option.common)?;
+        px_to_rem.direction = Direction::Convert(
+            option.from.unwrap_or(SourceUnit::Px),
+            option.to.unwrap_or(TargetUnit::Rem),
+        );
+        if let Some(viewport_width) = option.viewport_width {
+            px_to_rem.viewport_width = viewport_width;
+        }
+        if let Some(viewport_height) = option.viewport_height {
+            px_to_rem.viewport_height = viewport_height;
+        }
+        Ok(Self(px_to_rem))
+    }
+}
+
+delegate_px2rem!(UnitConverter);
+
+/// Unit produced by [`Px2Viewport`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportUnit {
+    Vw,
+    Vh,
+    Vmin,
+    Vmax,
+}
+
+impl ViewportUnit {
+    fn target_unit(&self) -> TargetUnit {
+        match self {
+            ViewportUnit::Vw => TargetUnit::Vw,
+            ViewportUnit::Vh => TargetUnit::Vh,
+            ViewportUnit::Vmin => TargetUnit::Vmin,
+            ViewportUnit::Vmax => TargetUnit::Vmax,
+        }
+    }
+}
+
+/// Width of the design draft when none is given, the default of postcss-px-to-viewport,
+/// shared by [`Px2Viewport`] and the `vw` and `rpx` targets of [`UnitConverter`]
+pub const DEFAULT_VIEWPORT_WIDTH: f64 = 320f64;
+
+/// Height of the design draft when none is given, the base of `vh`
+pub const DEFAULT_VIEWPORT_HEIGHT: f64 = 568f64;
+
+#[derive(Default)]
+pub struct Px2ViewportOption {
+    /// width of the design draft, `320` by default
+    pub viewport_width: Option<f64>,
+    /// height of the design draft, `568` by default
+    pub viewport_height: Option<f64>,
+    /// `vw` by default
+    pub viewport_unit: Option<ViewportUnit>,
+    /// when set, px inside `@media (orientation: landscape)` become `vw` of this width
+    pub landscape_width: Option<f64>,
+    /// prop list, selector black list, precision and so on, `root_value` is not used
+    pub common: Px2RemOption,
+}
+
+/// Converts px to viewport units with the postcss-px-to-viewport semantics, the `px` to
+/// viewport unit preset of [`UnitConverter`] with a landscape width on top
+#[derive(Debug, Clone)]
+pub struct Px2Viewport(Px2Rem);
+
+impl Px2Viewport {
+    /// panics if the option is invalid, see [`Px2Viewport::try_new`]
+    pub fn new(option: Px2ViewportOption) -> Self {
+        match Self::try_new(option) {
+            Ok(ret) => ret,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub fn try_new(option: Px2ViewportOption) -> Result<Self, Px2RemError> {
+        let mut px_to_rem = Px2Rem::try_new(option.common)?;
+        let unit = option.viewport_unit.unwrap_or(ViewportUnit::Vw);
+        px_to_rem.direction = Direction::Convert(SourceUnit::Px, unit.target_unit());
+        px_to_rem.viewport_width = option.viewport_width.unwrap_or(DEFAULT_VIEWPORT_WIDTH);
+        px_to_rem.viewport_height = option.viewport_height.unwrap_or(DEFAULT_VIEWPORT_HEIGHT);
+        px_to_rem.landscape_width = option.landscape_width;
+        Ok(Self(px_to_rem))
+    }
+}
+
+delegate_px2rem!(Px2Viewport);
+
+#[derive(Default, Debug, Clone)]
 pub struct MatchList {
     pub exact_list: Vec<SmolStr>,
     pub contain_list: Vec<SmolStr>,
@@ -255,95 +1256,192 @@ pub struct MatchList {
 
 impl<'a> VisitMut<'a> for Px2Rem {
     fn visit_root(&mut self, root: &mut recursive_parser::parser::Root<'a>) {
-        for child in root.children.iter_mut() {
-            match child {
+        let excluded = self
+            .from
+            .as_ref()
+            .is_some_and(|from| self.is_excluded(from));
+        let mut i = 0;
+        while !excluded && i < root.children.len() {
+            match &mut root.children[i] {
                 RuleOrAtRuleOrDecl::Rule(rule) => {
-                    if !self.blacklisted_selector(&rule.selector) {
-                        self.visit_rule(rule);
+                    if let Some(skipped) = self.visit_split_rule(rule) {
+                        root.children
+                            .insert(i + 1, RuleOrAtRuleOrDecl::Rule(skipped));
                     }
                 }
                 RuleOrAtRuleOrDecl::AtRule(at_rule) => {
                     self.visit_at_rule(at_rule);
                 }
-                RuleOrAtRuleOrDecl::Declaration(_) => unreachable!(),
+                // there is no selector to match against, leave it untouched
+                RuleOrAtRuleOrDecl::Declaration(_) => {}
             }
+            i += 1;
         }
+        // the offsets of the directives only make sense in the source just visited
+        self.directives = Directives::default();
     }
 
     fn visit_rule(&mut self, rule: &mut recursive_parser::parser::Rule<'a>) {
-        if rule.children.len() > 1 {
-            let mut vec = Vec::with_capacity(rule.children.len());
-            for child in rule.children.iter() {
-                if let RuleOrAtRuleOrDecl::Declaration(decl) = child {
-                    vec.push(((&decl.prop).into(), (&decl.value).into()));
-                }
-            }
-            self.map_stack.push(vec);
+        if self.directives.is_disabled(rule.start) {
+            self.count_skipped(&rule.children, |skipped| &mut skipped.disabled);
+            return;
         }
-        for child in rule.children.iter_mut() {
-            match child {
-                RuleOrAtRuleOrDecl::Rule(_rule) => {
-                    unimplemented!()
+        let selector = match self.selector_stack.last() {
+            Some(parent) => resolve_nested(parent, &rule.selector),
+            None => rule.selector.to_string(),
+        };
+        if self.blacklisted_selector(&selector) {
+            self.count_skipped(&rule.children, |skipped| &mut skipped.blacklisted_selector);
+            return;
+        }
+        let in_scope = self.whitelisted_selector(Some(&selector));
+        self.selector_stack.push(selector);
+        self.map_stack.push(declaration_map(&rule.children));
+        let mut i = 0;
+        while i < rule.children.len() {
+            match &mut rule.children[i] {
+                RuleOrAtRuleOrDecl::Rule(child) => {
+                    if let Some(skipped) = self.visit_split_rule(child) {
+                        rule.children
+                            .insert(i + 1, RuleOrAtRuleOrDecl::Rule(skipped));
+                    }
                 }
                 RuleOrAtRuleOrDecl::AtRule(at_rule) => {
                     self.visit_at_rule(at_rule);
                 }
                 RuleOrAtRuleOrDecl::Declaration(decl) => {
-                    self.visit_declaration(decl);
+                    if !in_scope {
+                        self.count_skipped_declaration(decl, |skipped| {
+                            &mut skipped.not_in_selector_white_list
+                        });
+                    } else if self.lint.is_some() {
+                        self.lint_declaration(decl);
+                    } else if self.replace {
+                        self.visit_declaration(decl);
+                    } else if let Some(converted) = self.converted_declaration(decl) {
+                        rule.children
+                            .insert(i + 1, RuleOrAtRuleOrDecl::Declaration(converted));
+                        i += 1;
+                    }
                 }
             }
+            i += 1;
         }
-        if rule.children.len() > 1 {
-            self.map_stack.pop();
-        }
+        self.map_stack.pop();
+        self.selector_stack.pop();
     }
 
     fn visit_at_rule(&mut self, at_rule: &mut recursive_parser::parser::AtRule<'a>) {
-        if self.media_query && at_rule.name == "media" && at_rule.params.contains("px") {
-            let value = self.px_replace(&at_rule.params).to_string();
-            at_rule.params = Cow::Owned(value);
+        if self.directives.is_disabled(at_rule.start) {
+            self.count_skipped(&at_rule.children, |skipped| &mut skipped.disabled);
+            return;
         }
-        for child in at_rule.children.iter_mut() {
-            match child {
+        let in_scope = self.whitelisted_selector(self.selector_stack.last().map(|s| s.as_str()));
+        if self
+            .at_rule_list
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(&at_rule.name))
+            && at_rule.params.contains(self.source_unit())
+        {
+            if in_scope {
+                self.convert_params(at_rule);
+            } else {
+                self.stats.borrow_mut().skipped.not_in_selector_white_list +=
+                    self.count_values(&at_rule.params);
+            }
+        }
+        let landscape = self.landscape_width.is_some()
+            && at_rule.name.eq_ignore_ascii_case("media")
+            && regex!(r"orientation\s*:\s*landscape").is_match(&at_rule.params);
+        if landscape {
+            self.landscape_depth += 1;
+        }
+        self.map_stack.push(declaration_map(&at_rule.children));
+        let mut i = 0;
+        while i < at_rule.children.len() {
+            match &mut at_rule.children[i] {
                 RuleOrAtRuleOrDecl::Rule(rule) => {
-                    self.visit_rule(rule);
+                    if let Some(skipped) = self.visit_split_rule(rule) {
+                        at_rule
+                            .children
+                            .insert(i + 1, RuleOrAtRuleOrDecl::Rule(skipped));
+                    }
                 }
                 RuleOrAtRuleOrDecl::AtRule(at_rule) => {
                     self.visit_at_rule(at_rule);
                 }
                 RuleOrAtRuleOrDecl::Declaration(decl) => {
-                    self.visit_declaration(decl);
+                    if !in_scope {
+                        self.count_skipped_declaration(decl, |skipped| {
+                            &mut skipped.not_in_selector_white_list
+                        });
+                    } else if self.lint.is_some() {
+                        self.lint_declaration(decl);
+                    } else if self.replace {
+                        self.visit_declaration(decl);
+                    } else if let Some(converted) = self.converted_declaration(decl) {
+                        at_rule
+                            .children
+                            .insert(i + 1, RuleOrAtRuleOrDecl::Declaration(converted));
+                        i += 1;
+                    }
                 }
             }
+            i += 1;
+        }
+        self.map_stack.pop();
+        if landscape {
+            self.landscape_depth -= 1;
         }
     }
 
     fn visit_declaration(&mut self, decl: &mut recursive_parser::parser::Declaration<'a>) {
-        if !decl.value.contains("px") {
-            return;
-        }
-        if !self.is_match(&decl.prop) {
-            return;
+        if let Some(value) = self.converted_value(decl) {
+            decl.value = Cow::Owned(value);
         }
-        let value = self.px_replace(&decl.value).to_string();
-        if let Some(vec) = self.map_stack.last() {
-            if vec
-                .iter()
-                .any(|(k, v)| k.as_str() == decl.prop && v.as_str() == value)
-            {
-                return;
-            }
-        }
-        // TODO: decide replace or insert after
-        decl.value = Cow::Owned(value);
     }
 }
 
+/// The declarations directly under a rule or at-rule, a converted value already among them
+/// is not added again
+fn declaration_map(children: &[RuleOrAtRuleOrDecl]) -> Vec<(SmolStr, SmolStr)> {
+    children
+        .iter()
+        .filter_map(|child| match child {
+            RuleOrAtRuleOrDecl::Declaration(decl) => {
+                Some(((&decl.prop).into(), (&decl.value).into()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether `at_rule` is a statement ending with `;` such as `@import url(x.css);`
+/// rather than a block, the tree does not tell `@layer a;` from `@layer a {}`
+/// so an empty body counts as none for the at-rules that may go without one
+fn is_bodiless(at_rule: &AtRule) -> bool {
+    at_rule.children.is_empty()
+        && [
+            "import",
+            "charset",
+            "namespace",
+            "layer",
+            "use",
+            "forward",
+            "custom-media",
+        ]
+        .iter()
+        .any(|name| at_rule.name.eq_ignore_ascii_case(name))
+}
+
 #[derive(Default)]
 pub struct SimplePrettier<W: Write> {
     level: usize,
     pub writer: W,
     indent: usize,
+    /// number of lines written so far
+    line: usize,
+    source_map: Option<SourceMapBuilder>,
 }
 
 impl<W: Write> SimplePrettier<W> {
@@ -352,8 +1450,33 @@ impl<W: Write> SimplePrettier<W> {
             level: 0,
             writer,
             indent,
+            line: 0,
+            source_map: None,
         }
     }
+
+    /// Record where every printed rule, at-rule and declaration comes from,
+    /// the offsets of the nodes must point into the source of `builder`
+    pub fn with_source_map(mut self, builder: SourceMapBuilder) -> Self {
+        self.source_map = Some(builder);
+        self
+    }
+
+    /// The source map of what has been printed, `file` is the name of the output
+    pub fn source_map(&mut self, file: Option<&str>) -> Option<SourceMap> {
+        self.source_map.take().map(|builder| builder.build(file))
+    }
+
+    /// Write one indented line, mapped to byte offset `start` of the source if any
+    fn write_line(&mut self, start: Option<usize>, text: fmt::Arguments) -> std::io::Result<()> {
+        let indent = self.level * self.indent;
+        if let (Some(builder), Some(start)) = (self.source_map.as_mut(), start) {
+            builder.add_mapping(self.line, indent, start);
+        }
+        let line = format!("{}{}\n", " ".repeat(indent), text);
+        self.line += line.matches('\n').count();
+        self.writer.write_all(line.as_bytes())
+    }
 }
 
 impl<'a, W: std::io::Write> VisitMut<'a, std::io::Result<()>> for SimplePrettier<W> {
@@ -366,8 +1489,8 @@ impl<'a, W: std::io::Write> VisitMut<'a, std::io::Result<()>> for SimplePrettier
                 RuleOrAtRuleOrDecl::AtRule(at_rule) => {
                     self.visit_at_rule(at_rule)?;
                 }
-                RuleOrAtRuleOrDecl::Declaration(_) => {
-                    unreachable!()
+                RuleOrAtRuleOrDecl::Declaration(decl) => {
+                    self.visit_declaration(decl)?;
                 }
             }
         }
@@ -375,20 +1498,12 @@ impl<'a, W: std::io::Write> VisitMut<'a, std::io::Result<()>> for SimplePrettier
     }
 
     fn visit_rule(&mut self, rule: &mut Rule<'a>) -> std::io::Result<()> {
-        self.writer.write_all(
-            format!(
-                "{}{} {}\n",
-                " ".repeat(self.level * self.indent),
-                rule.selector,
-                "{"
-            )
-            .as_bytes(),
-        )?;
+        self.write_line(Some(rule.start), format_args!("{} {{", rule.selector))?;
         self.level += 1;
         for child in rule.children.iter_mut() {
             match child {
-                RuleOrAtRuleOrDecl::Rule(_) => {
-                    unreachable!()
+                RuleOrAtRuleOrDecl::Rule(rule) => {
+                    self.visit_rule(rule)?;
                 }
                 RuleOrAtRuleOrDecl::AtRule(at_rule) => {
                     self.visit_at_rule(at_rule)?;
@@ -399,17 +1514,20 @@ impl<'a, W: std::io::Write> VisitMut<'a, std::io::Result<()>> for SimplePrettier
             }
         }
         self.level -= 1;
-        writeln!(self.writer, "{}}}", " ".repeat(self.level * self.indent))?;
-        Ok(())
+        self.write_line(None, format_args!("}}"))
     }
 
     fn visit_at_rule(&mut self, at_rule: &mut AtRule<'a>) -> std::io::Result<()> {
-        writeln!(
-            self.writer,
-            "{}@{} {} {{",
-            " ".repeat(self.level * self.indent),
-            at_rule.name,
-            at_rule.params
+        let separator = if at_rule.params.is_empty() { "" } else { " " };
+        if is_bodiless(at_rule) {
+            return self.write_line(
+                Some(at_rule.start),
+                format_args!("@{}{}{};", at_rule.name, separator, at_rule.params),
+            );
+        }
+        self.write_line(
+            Some(at_rule.start),
+            format_args!("@{}{}{} {{", at_rule.name, separator, at_rule.params),
         )?;
         self.level += 1;
         for child in at_rule.children.iter_mut() {
@@ -420,22 +1538,382 @@ impl<'a, W: std::io::Write> VisitMut<'a, std::io::Result<()>> for SimplePrettier
                 RuleOrAtRuleOrDecl::AtRule(at_rule) => {
                     self.visit_at_rule(at_rule)?;
                 }
-                RuleOrAtRuleOrDecl::Declaration(_decl) => {
-                    //   self.visit_declaration(decl);
+                RuleOrAtRuleOrDecl::Declaration(decl) => {
+                    self.visit_declaration(decl)?;
                 }
             }
         }
         self.level -= 1;
-        writeln!(self.writer, "{}}}", " ".repeat(self.level * self.indent))
+        self.write_line(None, format_args!("}}"))
     }
 
     fn visit_declaration(&mut self, decl: &mut Declaration<'a>) -> std::io::Result<()> {
-        writeln!(
-            self.writer,
-            "{}{}: {};",
-            " ".repeat(self.level * self.indent),
-            decl.prop,
-            decl.value
+        self.write_line(
+            Some(decl.start),
+            format_args!("{}: {};", decl.prop, decl.value),
         )
     }
 }
+
+/// Reproduces the source byte for byte, comments and whitespace included,
+/// except for the tokens of the values and at-rule params the transform rewrote.
+/// Declarations inserted when `replace` is false are written right after the one they copy
+/// and the comments trailing it, so are the rules split off by `split_selector_list`
+pub struct LosslessPrinter<'s, W: Write> {
+    pub writer: W,
+    source: &'s str,
+    /// the rewrites of the last visited root, ordered by offset
+    edits: Vec<Edit>,
+    /// span of the last declaration seen, a copy inserted after it shares its span
+    last_declaration: Option<(usize, usize)>,
+    /// start of the last rule visited, a copy split off from it shares its start
+    last_rule: Option<usize>,
+}
+
+impl<'s, W: Write> LosslessPrinter<'s, W> {
+    /// `source` must be the text the visited root was parsed from
+    pub fn new(writer: W, source: &'s str) -> Self {
+        Self {
+            writer,
+            source,
+            edits: vec![],
+            last_declaration: None,
+            last_rule: None,
+        }
+    }
+
+    /// The rewrites applied to the source, e.g. to build a diff with [`crate::diff::unified_diff`]
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// The span of the declaration text without the trailing semicolon and whitespace
+    fn declaration_span(&self, start: usize, end: usize) -> (usize, usize) {
+        let text = self.source[start..end.min(self.source.len())].trim_end();
+        let text = text.strip_suffix(';').unwrap_or(text).trim_end();
+        (start, start + text.len())
+    }
+
+    /// The span of the selector of the rule starting at `start`
+    fn selector_span(&self, start: usize, end: usize) -> (usize, usize) {
+        let header = &self.source[start..end.min(self.source.len())];
+        let header = &header[..header.find('{').unwrap_or(header.len())];
+        (start, start + header.trim_end().len())
+    }
+
+    /// The span of the value within the declaration span
+    fn value_span(&self, (start, end): (usize, usize)) -> Option<(usize, usize)> {
+        let text = &self.source[start..end];
+        let colon = text.find(':')?;
+        let value = text[colon + 1..].trim_start();
+        Some((end - value.len(), end))
+    }
+
+    /// Rewrite the source between `start` and `end` into `value`, a rewrite of it by the
+    /// transform, one token at a time so the comments and whitespace in between are kept
+    fn rewrite(&self, start: usize, end: usize, value: &str) -> Vec<Edit> {
+        let source = tokens(&self.source[start..end]);
+        let rewritten = tokens(value);
+        if source.len() != rewritten.len() {
+            // the parser changed more than the whitespace, replace it all
+            return vec![Edit {
+                start,
+                end,
+                text: value.to_string(),
+            }];
+        }
+        source
+            .into_iter()
+            .zip(rewritten)
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((offset, before), (_, after))| Edit {
+                start: start + offset,
+                end: start + offset + before.len(),
+                text: after.to_string(),
+            })
+            .collect()
+    }
+
+    /// Where a copy of the declaration ending at `end` is inserted,
+    /// after its semicolon and the comments on the same line
+    fn insertion_point(&self, end: usize) -> Option<usize> {
+        let rest = &self.source[end..];
+        let mut point = end
+            + rest
+                .trim_start()
+                .strip_prefix(';')
+                .map(|after| rest.len() - after.len())?;
+        loop {
+            let rest = &self.source[point..];
+            let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            match rest[spaces..]
+                .strip_prefix("/*")
+                .and_then(|comment| comment.find("*/"))
+            {
+                Some(close) => point += spaces + 2 + close + 2,
+                None => return Some(point),
+            }
+        }
+    }
+}
+
+/// The tokens of a value with their offsets, leaving out comments and whitespace
+fn tokens(value: &str) -> Vec<(usize, &str)> {
+    regex!(r#"/\*(?s:.*?)\*/|\s+|"(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'|[-+]?\d*\.?\d+(?:e[-+]?\d+)?[a-zA-Z%]*|[\w-]+|."#)
+        .find_iter(value)
+        .filter(|token| !token.as_str().starts_with("/*") && !token.as_str().trim().is_empty())
+        .map(|token| (token.start(), token.as_str()))
+        .collect()
+}
+
+impl<'a, 's, W: Write> VisitMut<'a, std::io::Result<()>> for LosslessPrinter<'s, W> {
+    fn visit_root(&mut self, root: &mut Root<'a>) -> std::io::Result<()> {
+        self.edits.clear();
+        self.last_declaration = None;
+        self.last_rule = None;
+        for child in root.children.iter_mut() {
+            match child {
+                RuleOrAtRuleOrDecl::Rule(rule) => {
+                    self.visit_rule(rule)?;
+                }
+                RuleOrAtRuleOrDecl::AtRule(at_rule) => {
+                    self.visit_at_rule(at_rule)?;
+                }
+                RuleOrAtRuleOrDecl::Declaration(decl) => {
+                    self.visit_declaration(decl)?;
+                }
+            }
+        }
+        let output = apply_edits(self.source, &self.edits);
+        self.writer.write_all(output.as_bytes())
+    }
+
+    fn visit_rule(&mut self, rule: &mut Rule<'a>) -> std::io::Result<()> {
+        let (start, selector_end) = self.selector_span(rule.start, rule.end);
+        if self.last_rule == Some(rule.start) {
+            // split off, nothing in it is converted so copy the source after the original
+            let end = rule.end.min(self.source.len());
+            let before = &self.source[..start];
+            let whitespace = match &before[before.trim_end().len()..] {
+                "" => "\n",
+                whitespace => whitespace,
+            };
+            self.edits.push(Edit {
+                start: end,
+                end,
+                text: format!(
+                    "{}{}{}",
+                    whitespace,
+                    rule.selector,
+                    &self.source[selector_end..end]
+                ),
+            });
+            return Ok(());
+        }
+        if let Cow::Owned(selector) = &rule.selector {
+            self.edits.push(Edit {
+                start,
+                end: selector_end,
+                text: selector.to_string(),
+            });
+        }
+        for child in rule.children.iter_mut() {
+            match child {
+                RuleOrAtRuleOrDecl::Rule(rule) => {
+                    self.visit_rule(rule)?;
+                }
+                RuleOrAtRuleOrDecl::AtRule(at_rule) => {
+                    self.visit_at_rule(at_rule)?;
+                }
+                RuleOrAtRuleOrDecl::Declaration(decl) => {
+                    self.visit_declaration(decl)?;
+                }
+            }
+        }
+        // set once the children are visited, they are not copies of each other
+        self.last_rule = Some(rule.start);
+        Ok(())
+    }
+
+    fn visit_at_rule(&mut self, at_rule: &mut AtRule<'a>) -> std::io::Result<()> {
+        if let Cow::Owned(params) = &at_rule.params {
+            let params_start = at_rule.start + 1 + at_rule.name.len();
+            let header = &self.source[params_start..at_rule.end.min(self.source.len())];
+            let header = &header[..header.find(['{', ';']).unwrap_or(header.len())];
+            let params_end = params_start + header.trim_end().len();
+            let params_start = params_end - header.trim().len();
+            let edits = self.rewrite(params_start, params_end, params);
+            self.edits.extend(edits);
+        }
+        for child in at_rule.children.iter_mut() {
+            match child {
+                RuleOrAtRuleOrDecl::Rule(rule) => {
+                    self.visit_rule(rule)?;
+                }
+                RuleOrAtRuleOrDecl::AtRule(at_rule) => {
+                    self.visit_at_rule(at_rule)?;
+                }
+                RuleOrAtRuleOrDecl::Declaration(decl) => {
+                    self.visit_declaration(decl)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_declaration(&mut self, decl: &mut Declaration<'a>) -> std::io::Result<()> {
+        let span = self.declaration_span(decl.start, decl.end);
+        if self.last_declaration == Some(span) {
+            // a converted copy, insert it after the original with the same leading whitespace
+            let (start, end) = span;
+            let before = &self.source[..start];
+            let whitespace = match &before[before.trim_end().len()..] {
+                "" => " ",
+                whitespace => whitespace,
+            };
+            let copy = match self.value_span(span) {
+                Some((value_start, value_end)) => {
+                    let edits = self
+                        .rewrite(value_start, value_end, &decl.value)
+                        .into_iter()
+                        .map(|edit| Edit {
+                            start: edit.start - start,
+                            end: edit.end - start,
+                            text: edit.text,
+                        })
+                        .collect::<Vec<_>>();
+                    apply_edits(&self.source[start..end], &edits)
+                }
+                None => self.source[start..end].to_string(),
+            };
+            let edit = match self.insertion_point(end) {
+                Some(point) => Edit {
+                    start: point,
+                    end: point,
+                    text: format!("{}{};", whitespace, copy),
+                },
+                None => Edit {
+                    start: end,
+                    end,
+                    text: format!(";{}{}", whitespace, copy),
+                },
+            };
+            self.edits.push(edit);
+            return Ok(());
+        }
+        self.last_declaration = Some(span);
+        if let Cow::Owned(value) = &decl.value {
+            if let Some((value_start, value_end)) = self.value_span(span) {
+                let edits = self.rewrite(value_start, value_end, value);
+                self.edits.extend(edits);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the whole stylesheet on one line without optional whitespace and semicolons,
+/// e.g. `.a{margin:.5rem 0;color:red}`
+#[derive(Default)]
+pub struct MinifiedPrinter<W: Write> {
+    pub writer: W,
+    /// a declaration was written last, so the next node needs a semicolon before it
+    need_semicolon: bool,
+}
+
+impl<W: Write> MinifiedPrinter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            need_semicolon: false,
+        }
+    }
+
+    fn separate(&mut self) -> std::io::Result<()> {
+        if self.need_semicolon {
+            self.writer.write_all(b";")?;
+            self.need_semicolon = false;
+        }
+        Ok(())
+    }
+
+    fn visit_children<'a>(
+        &mut self,
+        children: &mut [RuleOrAtRuleOrDecl<'a>],
+    ) -> std::io::Result<()> {
+        for child in children.iter_mut() {
+            self.separate()?;
+            match child {
+                RuleOrAtRuleOrDecl::Rule(rule) => {
+                    self.visit_rule(rule)?;
+                }
+                RuleOrAtRuleOrDecl::AtRule(at_rule) => {
+                    self.visit_at_rule(at_rule)?;
+                }
+                RuleOrAtRuleOrDecl::Declaration(decl) => {
+                    self.visit_declaration(decl)?;
+                }
+            }
+        }
+        self.need_semicolon = false;
+        Ok(())
+    }
+}
+
+/// Collapse whitespace outside of strings and drop the leading zero of fractions,
+/// e.g. `0 0.5rem  -0.25rem !important` becomes `0 .5rem -.25rem!important`
+fn minify_value(value: &str) -> String {
+    regex!(r#""[^"]*"|'[^']*'|url\([^)]*\)|\s*!\s*((?i:important))$|(\s+)|\b0\.(\d)"#)
+        .replace_all(value.trim(), |caps: &Captures| {
+            if let Some(important) = caps.get(1) {
+                format!("!{}", important.as_str())
+            } else if caps.get(2).is_some() {
+                " ".to_string()
+            } else if let Some(digit) = caps.get(3) {
+                format!(".{}", digit.as_str())
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+impl<'a, W: std::io::Write> VisitMut<'a, std::io::Result<()>> for MinifiedPrinter<W> {
+    fn visit_root(&mut self, root: &mut Root<'a>) -> std::io::Result<()> {
+        self.need_semicolon = false;
+        self.visit_children(&mut root.children)
+    }
+
+    fn visit_rule(&mut self, rule: &mut Rule<'a>) -> std::io::Result<()> {
+        let selector = split_list(&rule.selector)
+            .into_iter()
+            .map(|selector| regex!(r"\s+").replace_all(selector, " "))
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(self.writer, "{}{{", selector)?;
+        self.visit_children(&mut rule.children)?;
+        self.writer.write_all(b"}")
+    }
+
+    fn visit_at_rule(&mut self, at_rule: &mut AtRule<'a>) -> std::io::Result<()> {
+        let params = minify_value(&at_rule.params);
+        if params.is_empty() {
+            write!(self.writer, "@{}", at_rule.name)?;
+        } else {
+            write!(self.writer, "@{} {}", at_rule.name, params)?;
+        }
+        if is_bodiless(at_rule) {
+            self.need_semicolon = true;
+            return Ok(());
+        }
+        self.writer.write_all(b"{")?;
+        self.visit_children(&mut at_rule.children)?;
+        self.writer.write_all(b"}")
+    }
+
+    fn visit_declaration(&mut self, decl: &mut Declaration<'a>) -> std::io::Result<()> {
+        write!(self.writer, "{}:{}", decl.prop, minify_value(&decl.value))?;
+        self.need_semicolon = true;
+        Ok(())
+    }
+}
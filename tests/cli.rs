@@ -0,0 +1,165 @@
+use assert_cmd::Command;
+use std::fs;
+use tempfile::TempDir;
+
+fn px2rem(dir: &TempDir) -> Command {
+    let mut cmd = Command::cargo_bin("px2rem").unwrap();
+    cmd.current_dir(dir.path());
+    cmd
+}
+
+fn write(dir: &TempDir, path: &str, css: &str) {
+    let path = dir.path().join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, css).unwrap();
+}
+
+fn read(dir: &TempDir, path: &str) -> String {
+    fs::read_to_string(dir.path().join(path)).unwrap()
+}
+
+const CSS: &str = ".a { font-size: 16px }\n";
+const CONVERTED: &str = ".a {\n  font-size: 1rem;\n}\n";
+
+#[test]
+fn test_stdout() {
+    let dir = TempDir::new().unwrap();
+    write(&dir, "a.css", CSS);
+    px2rem(&dir)
+        .arg("a.css")
+        .assert()
+        .success()
+        .stdout(CONVERTED);
+}
+
+#[test]
+fn test_out_dir_keeps_paths_under_glob_base() {
+    let dir = TempDir::new().unwrap();
+    write(&dir, "src/a/x.css", CSS);
+    write(&dir, "src/b/x.css", CSS);
+    px2rem(&dir)
+        .args(["src/**/*.css", "-o", "dist"])
+        .assert()
+        .success();
+    assert_eq!(read(&dir, "dist/a/x.css"), CONVERTED);
+    assert_eq!(read(&dir, "dist/b/x.css"), CONVERTED);
+}
+
+#[test]
+fn test_out_dir_keeps_paths_under_common_ancestor() {
+    let dir = TempDir::new().unwrap();
+    write(&dir, "src/a/x.css", CSS);
+    write(&dir, "src/b/y.css", CSS);
+    px2rem(&dir)
+        .args(["src/a/x.css", "src/b/y.css", "--out-dir", "dist"])
+        .assert()
+        .success();
+    assert_eq!(read(&dir, "dist/a/x.css"), CONVERTED);
+    assert_eq!(read(&dir, "dist/b/y.css"), CONVERTED);
+}
+
+#[test]
+fn test_out_dir_collision() {
+    let dir = TempDir::new().unwrap();
+    write(&dir, "a/x.css", CSS);
+    write(&dir, "b/x.css", CSS);
+    px2rem(&dir)
+        .args(["a", "b", "-o", "dist"])
+        .assert()
+        .code(2)
+        .stderr("px2rem: a/x.css and b/x.css would both be written to dist/x.css\n");
+    assert!(!dir.path().join("dist").exists());
+}
+
+#[test]
+fn test_in_place() {
+    let dir = TempDir::new().unwrap();
+    write(&dir, "a.css", CSS);
+    px2rem(&dir)
+        .args(["a.css", "--in-place"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(read(&dir, "a.css"), CONVERTED);
+}
+
+#[test]
+fn test_check() {
+    let dir = TempDir::new().unwrap();
+    write(&dir, "a.css", CSS);
+    write(&dir, "b.css", ".b { font-size: 1rem }\n");
+    px2rem(&dir)
+        .args(["a.css", "--check"])
+        .assert()
+        .code(1)
+        .stdout("a.css:1:6: font-size: 16px, use 1rem\n");
+    px2rem(&dir)
+        .args(["b.css", "--check"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(read(&dir, "a.css"), CSS);
+}
+
+#[test]
+fn test_dry_run() {
+    let dir = TempDir::new().unwrap();
+    write(&dir, "a.css", CSS);
+    px2rem(&dir)
+        .args(["a.css", "--dry-run", "--format", "lossless"])
+        .assert()
+        .code(1)
+        .stdout(
+            "--- a/a.css\n+++ b/a.css\n@@ -1 +1 @@\n-.a { font-size: 16px }\n+.a { font-size: 1rem }\n",
+        );
+    assert_eq!(read(&dir, "a.css"), CSS);
+}
+
+#[test]
+fn test_dry_run_ignores_format() {
+    let dir = TempDir::new().unwrap();
+    let css =
+        ".a {\n  font-size: 16px; /* title */\n  color: red;\n}\n\n.b { line-height: 32px }\n";
+    write(&dir, "a.css", css);
+    write(
+        &dir,
+        "b.css",
+        "/* nothing to convert */\n.c { color: red }\n",
+    );
+    px2rem(&dir)
+        .args(["a.css", "b.css", "--dry-run", "--format", "pretty"])
+        .assert()
+        .code(1)
+        .stdout(
+            "--- a/a.css\n+++ b/a.css\n@@ -2 +2 @@\n-  font-size: 16px; /* title */\n+  font-size: 1rem; /* title */\n@@ -6 +6 @@\n-.b { line-height: 32px }\n+.b { line-height: 2rem }\n",
+        );
+    px2rem(&dir)
+        .args(["b.css", "--dry-run", "--format", "minified"])
+        .assert()
+        .code(0)
+        .stdout("");
+    assert_eq!(read(&dir, "a.css"), css);
+}
+
+#[test]
+fn test_missing_input() {
+    let dir = TempDir::new().unwrap();
+    px2rem(&dir)
+        .arg("missing.css")
+        .assert()
+        .code(2)
+        .stderr("px2rem: missing.css: no such file or directory\n");
+}
+
+#[test]
+fn test_source_map_sources_relative_to_map() {
+    let dir = TempDir::new().unwrap();
+    write(&dir, "src/a.css", CSS);
+    px2rem(&dir)
+        .args(["src/a.css", "-o", "dist/css", "--source-map", "file"])
+        .assert()
+        .success();
+    let map = read(&dir, "dist/css/a.css.map");
+    assert!(map.contains(r#""sources":["../../src/a.css"]"#), "{}", map);
+    assert!(read(&dir, "dist/css/a.css").ends_with("/*# sourceMappingURL=a.css.map */\n"));
+}
@@ -223,35 +223,66 @@ mod test_root_value {
             get_transformed_content_new(
                 BASIC_CSS,
                 Px2RemOption {
-                    root_value: Some(10),
+                    root_value: Some(10f64),
                     ..Default::default()
                 }
             )
         );
     }
 
-    // TODO: maybe this should handled by loader or cli?
-    // it("should replace using different root values with different files", function() {
-    //     var css2 = ".rule { font-size: 20px }";
-    //     var expected = ".rule { font-size: 1rem }";
-    //     var options = {
-    //       rootValue: function(input) {
-    //         if (input.from.indexOf("basic.css") !== -1) {
-    //           return 15;
-    //         }
-    //         return 20;
-    //       }
-    //     };
-    //     var processed1 = postcss(pxtorem(options)).process(basicCSS, {
-    //       from: "/tmp/basic.css"
-    //     }).css;
-    //     var processed2 = postcss(pxtorem(options)).process(css2, {
-    //       from: "/tmp/whatever.css"
-    //     }).css;
-
-    //     expect(processed1).toBe(expected);
-    //     expect(processed2).toBe(expected);
-    //   });
+    #[test]
+    fn test_fractional_root_value() {
+        let input = ".rule { font-size: 15px; width: 375px; }";
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 0.4rem;
+            width: 10rem;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    root_value: Some(37.5),
+                    prop_list: Some(vec!["*".to_string()]),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_different_root_values_with_different_files() {
+        let css2 = ".rule { font-size: 20px }";
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 1rem;
+        }
+        "#,
+        );
+        let option = || Px2RemOption {
+            root_value_resolver: Some(Box::new(|from: &str| {
+                if from.contains("basic.css") {
+                    15f64
+                } else {
+                    20f64
+                }
+            })),
+            ..Default::default()
+        };
+        assert_str_eq!(
+            expected,
+            get_transformed_content_from(BASIC_CSS, option(), "/tmp/basic.css")
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_from(css2, option(), "/tmp/whatever.css")
+        );
+    }
 }
 
 #[cfg(test)]
@@ -390,6 +421,39 @@ mod test_prop_list {
             )
         );
     }
+    #[test]
+    fn test_overlapping_patterns_on_repeated_props() {
+        let input = ".a { border-top: 16px; top: 16px; margin-top: 16px } .b { border-top: 32px; top: 32px; margin-top: 32px }";
+        let expected = unindent(
+            r#"
+        .a {
+            border-top: 1rem;
+            top: 16px;
+            margin-top: 16px;
+        }
+        .b {
+            border-top: 2rem;
+            top: 32px;
+            margin-top: 32px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    prop_list: Some(vec![
+                        "*top".to_string(),
+                        "border*".to_string(),
+                        "!top".to_string(),
+                        "!*gin*".to_string()
+                    ]),
+                    ..Default::default()
+                }
+            )
+        );
+    }
     // ignore this case, since we don't have legacy option
     //     it("should replace all properties when white list is empty", function() {
     //     var rules = ".rule { margin: 16px; font-size: 15px }";
@@ -493,36 +557,17 @@ mod test_selector_black_list {
             )
         );
     }
-}
-
-// We don't consider replace now, just think such `.test {width: 16px; width: 1rem;}` is meaningless
-// will execute as follow `.test {width: 1rem}`
-
-// describe("replace", function() {
-//     it("should leave fallback pixel unit with root em value", function() {
-//       var options = {
-//         replace: false
-//       };
-//       var processed = postcss(pxtorem(options)).process(basicCSS).css;
-//       var expected = ".rule { font-size: 15px; font-size: 0.9375rem }";
-
-//       expect(processed).toBe(expected);
-//     });
-//   });
-
-#[cfg(test)]
-mod test_media_query {
-    use super::*;
 
     #[test]
-    fn test_replace_px_in_media_query() {
-        let input = "@media (min-width: 500px) { .rule { font-size: 16px } }";
+    fn test_ignore_rule_js_regex_literal() {
+        let input = "BODY { font-size: 16px; } .class-body { font-size: 16px; }";
         let expected = unindent(
             r#"
-        @media (min-width: 31.25rem) {
-            .rule {
-                font-size: 1rem;
-            }
+        BODY {
+            font-size: 16px;
+        }
+        .class-body {
+            font-size: 1rem;
         }
         "#,
         );
@@ -531,27 +576,73 @@ mod test_media_query {
             get_transformed_content_new(
                 input,
                 Px2RemOption {
-                    media_query: Some(true),
+                    selector_black_list: Some(vec![
+                        postcss_px2rem::transform::StringOrRegexp::from_js("/^body$/i").unwrap()
+                    ]),
                     ..Default::default()
                 }
             )
         );
     }
-}
 
-#[cfg(test)]
-mod test_min_pixel_value {
-    use super::*;
+    #[test]
+    fn test_js_regex_literal_conversion() {
+        use postcss_px2rem::transform::StringOrRegexp;
+        assert!(matches!(
+            StringOrRegexp::from_js(".rule2").unwrap(),
+            StringOrRegexp::String(string) if string == ".rule2"
+        ));
+        assert!(matches!(
+            StringOrRegexp::from_js(r"/^\.m-\/(?<name>x)/msu").unwrap(),
+            StringOrRegexp::Regexp(re) if re == r"(?ms)^\.m-/(?P<name>x)"
+        ));
+        assert_str_eq!(
+            StringOrRegexp::from_js("/(?<=a)b/")
+                .unwrap_err()
+                .to_string(),
+            "unsupported regexp `/(?<=a)b/`: lookbehind is not supported"
+        );
+        assert_str_eq!(
+            StringOrRegexp::from_js(r"/(a)\1/").unwrap_err().to_string(),
+            r"unsupported regexp `/(a)\1/`: backreferences are not supported"
+        );
+        assert!(StringOrRegexp::from_js("/[(?=]/").is_ok());
+        assert!(StringOrRegexp::from_js("/a/y").is_err());
+        assert!(matches!(
+            StringOrRegexp::from_js("/path/to").unwrap(),
+            StringOrRegexp::String(string) if string == "/path/to"
+        ));
+        assert!(matches!(
+            StringOrRegexp::from_js("/a/ii").unwrap(),
+            StringOrRegexp::String(string) if string == "/a/ii"
+        ));
+    }
 
     #[test]
-    fn test_not_replace_value_below_min_pixel_value() {
-        let input = ".rule { border: 1px solid #000; font-size: 16px; margin: 1px 10px; }";
+    fn test_split_selector_list() {
+        use postcss_px2rem::transform::StringOrRegexp;
+        let input = ".keep, .ignore { font-size: 16px } .ignore-me, div.ignore.a { font-size: 16px } .c { .ignore, & span { font-size: 32px } }";
         let expected = unindent(
             r#"
-        .rule {
-            border: 1px solid #000;
+        .keep {
             font-size: 1rem;
-            margin: 1px 0.625rem;
+        }
+        .ignore {
+            font-size: 16px;
+        }
+        .ignore-me {
+            font-size: 1rem;
+        }
+        div.ignore.a {
+            font-size: 16px;
+        }
+        .c {
+            & span {
+                font-size: 2rem;
+            }
+            .ignore {
+                font-size: 32px;
+            }
         }
         "#,
         );
@@ -560,221 +651,1967 @@ mod test_min_pixel_value {
             get_transformed_content_new(
                 input,
                 Px2RemOption {
-                    min_pixel_value: Some(2f64),
-                    prop_list: Some(vec!["*".to_string()]),
+                    selector_black_list: Some(vec![StringOrRegexp::String(".ignore".to_string())]),
+                    split_selector_list: Some(true),
                     ..Default::default()
                 }
             )
         );
     }
+
+    #[test]
+    fn test_regexp_flags_do_not_leak_into_other_patterns() {
+        use postcss_px2rem::transform::StringOrRegexp;
+        let px_to_rem = Px2Rem::new(Px2RemOption {
+            selector_black_list: Some(vec![
+                StringOrRegexp::from_js(r"/^\.ignore$/i").unwrap(),
+                StringOrRegexp::Regexp(r"^\.m-".to_string()),
+                StringOrRegexp::String("body".to_string()),
+            ]),
+            ..Default::default()
+        });
+        assert!(px_to_rem.blacklisted_selector(".IGNORE"));
+        assert!(px_to_rem.blacklisted_selector(".m-header"));
+        assert!(!px_to_rem.blacklisted_selector(".M-header"));
+        assert!(px_to_rem.blacklisted_selector("html body"));
+        assert!(!px_to_rem.blacklisted_selector(".rule"));
+    }
 }
 
 #[cfg(test)]
-mod test_filter_prop_list {
+mod test_selector_white_list {
     use super::*;
+    use postcss_px2rem::transform::StringOrRegexp;
 
     #[test]
-    fn test_exact() {
+    fn test_only_convert_rules_in_scope() {
+        let input = ".mobile-root { font-size: 16px; .a { font-size: 16px } } .mobile-root > .b { font-size: 16px } .m-c { font-size: 32px } .d { font-size: 16px; @media print { .mobile-root & { font-size: 32px } } }";
+        let expected = unindent(
+            r#"
+        .mobile-root {
+            font-size: 16px;
+            .a {
+                font-size: 1rem;
+            }
+        }
+        .mobile-root > .b {
+            font-size: 1rem;
+        }
+        .m-c {
+            font-size: 2rem;
+        }
+        .d {
+            font-size: 16px;
+            @media print {
+                .mobile-root & {
+                    font-size: 2rem;
+                }
+            }
+        }
+        "#,
+        );
         let mut px_to_rem = Px2Rem::new(Px2RemOption {
-            prop_list: Some(vec![
-                "font-size".to_string(),
-                "margin".to_string(),
-                "!padding".to_string(),
-                "*border*".to_string(),
-                "*".to_string(),
-                "*y".to_string(),
-                "!*font*".to_string(),
+            selector_white_list: Some(vec![
+                StringOrRegexp::String(".mobile-root *".to_string()),
+                StringOrRegexp::from_js(r"/^\.m-/").unwrap(),
             ]),
             ..Default::default()
         });
-        px_to_rem.generate_match_list();
-        assert_str_eq!(
-            px_to_rem.match_list.exact_list.join(","),
-            "font-size,margin"
+        let writer = print_converted(
+            input,
+            &mut px_to_rem,
+            SimplePrettier::new(WrapString::default(), 4),
         );
+        assert_str_eq!(expected, writer.writer.0);
+        assert_eq!(px_to_rem.stats().skipped.not_in_selector_white_list, 2);
     }
 
     #[test]
-    fn test_contain() {
-        let mut px_to_rem = Px2Rem::new(Px2RemOption {
-            prop_list: Some(vec![
-                "font-size".to_string(),
-                "*margin*".to_string(),
-                "!padding".to_string(),
-                "*border*".to_string(),
-                "*".to_string(),
-                "*y".to_string(),
-                "!*font*".to_string(),
-            ]),
-            ..Default::default()
-        });
-        px_to_rem.generate_match_list();
-        assert_str_eq!(px_to_rem.match_list.contain_list.join(","), "margin,border");
+    fn test_split_rules_partly_in_scope() {
+        let input = ".m-a, .b { font-size: 16px }";
+        let expected = unindent(
+            r#"
+        .m-a {
+            font-size: 1rem;
+        }
+        .b {
+            font-size: 16px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    selector_white_list: Some(vec![StringOrRegexp::Regexp(r"^\.m-".to_string())]),
+                    split_selector_list: Some(true),
+                    ..Default::default()
+                }
+            )
+        );
     }
 
     #[test]
-    fn test_start() {
+    fn test_empty_list_converts_everything() {
         let mut px_to_rem = Px2Rem::new(Px2RemOption {
-            prop_list: Some(vec![
-                "font-size".to_string(),
-                "*margin*".to_string(),
-                "!padding".to_string(),
-                "border*".to_string(),
-                "*".to_string(),
-                "*y".to_string(),
-                "!*font*".to_string(),
-            ]),
+            selector_white_list: Some(vec![]),
             ..Default::default()
         });
-        px_to_rem.generate_match_list();
-        assert_str_eq!(px_to_rem.match_list.starts_with_list.join(","), "border");
+        let writer = print_converted(
+            ".a { font-size: 16px }",
+            &mut px_to_rem,
+            SimplePrettier::new(WrapString::default(), 4),
+        );
+        assert_str_eq!(".a {\n    font-size: 1rem;\n}\n", writer.writer.0);
+        assert_eq!(px_to_rem.stats().skipped.not_in_selector_white_list, 0);
     }
 
     #[test]
-    fn test_end() {
-        let mut px_to_rem = Px2Rem::new(Px2RemOption {
-            prop_list: Some(vec![
-                "font-size".to_string(),
-                "*margin*".to_string(),
-                "!padding".to_string(),
-                "border*".to_string(),
-                "*".to_string(),
-                "*y".to_string(),
-                "!*font*".to_string(),
-            ]),
-            ..Default::default()
-        });
-        px_to_rem.generate_match_list();
-        assert_str_eq!(px_to_rem.match_list.ends_with_list.join(","), "y");
+    fn test_scope_does_not_match_siblings() {
+        let input = ".mobile-root + .a { font-size: 16px } .mobile-root ~ .b { font-size: 16px } .mobile-root>.c { font-size: 16px } .mobile-root-d .e { font-size: 16px }";
+        let expected = unindent(
+            r#"
+        .mobile-root + .a {
+            font-size: 16px;
+        }
+        .mobile-root ~ .b {
+            font-size: 16px;
+        }
+        .mobile-root>.c {
+            font-size: 1rem;
+        }
+        .mobile-root-d .e {
+            font-size: 16px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    selector_white_list: Some(vec![StringOrRegexp::String(
+                        ".mobile-root *".to_string()
+                    )]),
+                    ..Default::default()
+                }
+            )
+        );
     }
 
     #[test]
-    fn test_not_exact() {
+    fn test_leave_selectorless_contexts_alone() {
+        let input = "@font-face { font-size: 16px } @page { margin: 16px } @media (min-width: 320px) { .mobile-root .a { margin: 16px } } .mobile-root .b { @media (max-width: 640px) { .c { margin: 16px } } }";
+        let expected = unindent(
+            r#"
+        @font-face {
+            font-size: 16px;
+        }
+        @page {
+            margin: 16px;
+        }
+        @media (min-width: 320px) {
+            .mobile-root .a {
+                margin: 1rem;
+            }
+        }
+        .mobile-root .b {
+            @media (max-width: 40rem) {
+                .c {
+                    margin: 1rem;
+                }
+            }
+        }
+        "#,
+        );
         let mut px_to_rem = Px2Rem::new(Px2RemOption {
-            prop_list: Some(vec![
-                "font-size".to_string(),
-                "*margin*".to_string(),
-                "!padding".to_string(),
-                "border*".to_string(),
-                "*".to_string(),
-                "*y".to_string(),
-                "!*font*".to_string(),
-            ]),
+            selector_white_list: Some(vec![StringOrRegexp::String(".mobile-root *".to_string())]),
+            prop_list: Some(vec!["*".to_string()]),
+            media_query: Some(true),
             ..Default::default()
         });
-        px_to_rem.generate_match_list();
-        assert_str_eq!(px_to_rem.match_list.not_exact_list.join(","), "padding");
+        let writer = print_converted(
+            input,
+            &mut px_to_rem,
+            SimplePrettier::new(WrapString::default(), 4),
+        );
+        assert_str_eq!(expected, writer.writer.0);
+        assert_eq!(px_to_rem.stats().skipped.not_in_selector_white_list, 3);
     }
+}
+
+#[cfg(test)]
+mod test_replace {
+    use super::*;
 
     #[test]
-    fn test_not_start() {
-        let mut px_to_rem = Px2Rem::new(Px2RemOption {
-            prop_list: Some(vec![
-                "font-size".to_string(),
-                "*margin*".to_string(),
-                "!padding".to_string(),
-                "border*".to_string(),
-                "*".to_string(),
-                "*y".to_string(),
-                "!*font*".to_string(),
+    fn test_leave_fallback_pixel_unit_with_root_em_value() {
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 15px;
+            font-size: 0.9375rem;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                BASIC_CSS,
+                Px2RemOption {
+                    replace: Some(false),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_leave_fallback_in_nested_media_rule() {
+        let input = "@media print { .rule { font-size: 16px } .other { margin: 0 } }";
+        let expected = unindent(
+            r#"
+        @media print {
+            .rule {
+                font-size: 16px;
+                font-size: 1rem;
+            }
+            .other {
+                margin: 0;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    replace: Some(false),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_not_insert_if_rem_declaration_exists() {
+        let input = ".rule { font-size: 16px; font-size: 1rem; line-height: 24px; }";
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 16px;
+            font-size: 1rem;
+            line-height: 24px;
+            line-height: 1.5rem;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    replace: Some(false),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_nesting {
+    use super::*;
+    use postcss_px2rem::selector::resolve_nested;
+    use postcss_px2rem::transform::StringOrRegexp;
+
+    #[test]
+    fn test_replace_px_in_nested_rule() {
+        let input = ".card { font-size: 16px; & .title { font-size: 18px; } &:hover { line-height: 32px; } }";
+        let expected = unindent(
+            r#"
+        .card {
+            font-size: 1rem;
+            & .title {
+                font-size: 1.125rem;
+            }
+            &:hover {
+                line-height: 2rem;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(expected, get_transformed_content_default(input));
+    }
+
+    #[test]
+    fn test_single_nested_declaration_not_deduplicated_against_parent() {
+        let input = ".a { font-size: 1rem; color: red; & .b { font-size: 16px } @media print { font-size: 16px } }";
+        let expected = unindent(
+            r#"
+        .a {
+            font-size: 1rem;
+            color: red;
+            & .b {
+                font-size: 1rem;
+            }
+            @media print {
+                font-size: 1rem;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(expected, get_transformed_content_default(input));
+    }
+
+    #[test]
+    fn test_single_nested_fallback_not_deduplicated_against_parent() {
+        let input = ".a { font-size: 1rem; color: red; @media print { .b { font-size: 16px } } }";
+        let expected = unindent(
+            r#"
+        .a {
+            font-size: 1rem;
+            color: red;
+            @media print {
+                .b {
+                    font-size: 16px;
+                    font-size: 1rem;
+                }
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    replace: Some(false),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_ignore_nested_rule_when_resolved_selector_in_black_list() {
+        let input =
+            ".card { font-size: 16px; & .title { font-size: 18px; } & .body { font-size: 18px; } }";
+        let expected = unindent(
+            r#"
+        .card {
+            font-size: 1rem;
+            & .title {
+                font-size: 18px;
+            }
+            & .body {
+                font-size: 1.125rem;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    selector_black_list: Some(vec![StringOrRegexp::Regexp(
+                        r"^\.card \.title$".to_string()
+                    )]),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_resolve_nested_selector() {
+        assert_str_eq!(resolve_nested(".a, .b", "& .c"), ".a .c, .b .c");
+        assert_str_eq!(resolve_nested(".a", ".c, &.d"), ".a .c, .a.d");
+        assert_str_eq!(resolve_nested(".a", ":is(.b, .c) &"), ":is(.b, .c) .a");
+    }
+}
+
+#[cfg(test)]
+mod test_error {
+    use super::*;
+    use postcss_px2rem::error::Px2RemError;
+    use postcss_px2rem::transform::{Exclude, StringOrRegexp};
+
+    #[test]
+    fn test_invalid_selector_black_list() {
+        let err = Px2Rem::try_new(Px2RemOption {
+            selector_black_list: Some(vec![
+                StringOrRegexp::String(".ok".to_string()),
+                StringOrRegexp::Regexp("^body(".to_string()),
+            ]),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(
+            matches!(err, Px2RemError::InvalidSelectorBlackList { ref pattern, .. } if pattern == "^body(")
+        );
+    }
+
+    #[test]
+    fn test_invalid_exclude_glob() {
+        let err = Px2Rem::try_new(Px2RemOption {
+            exclude: Some(Exclude::Glob("a/***".to_string())),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(err, Px2RemError::InvalidExcludeGlob { .. }));
+    }
+
+    #[test]
+    fn test_top_level_declaration() {
+        let mut root = parse("font-size: 16px; .rule { font-size: 16px; }", None);
+        let mut px_to_rem = Px2Rem::default();
+        px_to_rem.generate_match_list();
+        let err = px_to_rem.try_visit_root(&mut root).unwrap_err();
+        assert_str_eq!(
+            err.to_string(),
+            "unexpected declaration `font-size: 16px` outside of any rule"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_config {
+    use super::*;
+    use postcss_px2rem::config;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_json_with_pxtorem_keys() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let path = dir.join("px2rem.config.json");
+        fs::write(
+            &path,
+            r#"{ "rootValue": 10, "unitPrecision": 2, "propList": ["*"], "selectorBlackList": [".ignore"], "minPixelValue": 2 }"#,
+        )
+        .unwrap();
+        let input = ".rule { margin: 1px 15px } .ignore { margin: 15px }";
+        let expected = unindent(
+            r#"
+        .rule {
+            margin: 1px 1.5rem;
+        }
+        .ignore {
+            margin: 15px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(input, config::load(&path).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_load_toml_px2remrc() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        let path = dir.join(".px2remrc");
+        fs::write(&path, "rootValue = 10\nmediaQuery = true\n").unwrap();
+        let option = config::load(&path).unwrap();
+        assert_eq!(option.root_value, Some(10f64));
+        assert_eq!(option.media_query, Some(true));
+    }
+
+    #[test]
+    fn test_find_package_json_key() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        fs::write(
+            dir.join("package.json"),
+            r#"{ "name": "app", "px2rem": { "propList": ["font*"] } }"#,
+        )
+        .unwrap();
+        let nested = dir.join("src/styles");
+        fs::create_dir_all(&nested).unwrap();
+        let (path, option) = config::find(&nested).unwrap().unwrap();
+        assert_eq!(path, dir.join("package.json"));
+        assert_eq!(option.prop_list, Some(vec!["font*".to_string()]));
+    }
+
+    #[test]
+    fn test_find_skip_package_json_without_key() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join(".px2remrc"), r#"{ "rootValue": 20 }"#).unwrap();
+        let nested = dir.join("app");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("package.json"), r#"{ "name": "app" }"#).unwrap();
+        let (path, option) = config::find(&nested).unwrap().unwrap();
+        assert_eq!(path, dir.join(".px2remrc"));
+        assert_eq!(option.root_value, Some(20f64));
+    }
+}
+
+#[cfg(test)]
+mod test_directive {
+    use super::*;
+
+    #[test]
+    fn test_directives_do_not_carry_over_to_next_stylesheet() {
+        let first = "/* px2rem-disable */ .a { font-size: 16px }";
+        let second = ".b { font-size: 16px }";
+        let mut px_to_rem = Px2Rem::new(Default::default());
+        px_to_rem.set_source(first);
+        px_to_rem.visit_root(&mut parse(first, None));
+        let writer = print_converted(
+            second,
+            &mut px_to_rem,
+            SimplePrettier::new(WrapString::default(), 4),
+        );
+        assert_str_eq!(".b {\n    font-size: 1rem;\n}\n", writer.writer.0);
+    }
+
+    #[test]
+    fn test_disable_next_line() {
+        let input = unindent(
+            r#"
+        .rule {
+            /* px2rem-disable-next-line */
+            font-size: 16px;
+            line-height: 16px;
+        }
+        /* px2rem-disable-next-line */
+        .keep {
+            font-size: 16px;
+        }
+        "#,
+        );
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 16px;
+            line-height: 1rem;
+        }
+        .keep {
+            font-size: 16px;
+        }
+        "#,
+        );
+        assert_str_eq!(expected, get_transformed_content_default(&input));
+    }
+
+    #[test]
+    fn test_disable_enable_block() {
+        let input = unindent(
+            r#"
+        /* px2rem-disable */
+        .a { font-size: 16px; }
+        @media (min-width: 500px) { .b { font-size: 16px; } }
+        /* px2rem-enable */
+        .c { font-size: 16px; }
+        "#,
+        );
+        let expected = unindent(
+            r#"
+        .a {
+            font-size: 16px;
+        }
+        @media (min-width: 500px) {
+            .b {
+                font-size: 16px;
+            }
+        }
+        .c {
+            font-size: 1rem;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                &input,
+                Px2RemOption {
+                    media_query: Some(true),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_trailing_no_comment() {
+        let input = ".rule { font-size: 16px; /* no */ line-height: 16px; content: '/* no */'; }";
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 16px;
+            line-height: 1rem;
+            content: '/* no */';
+        }
+        "#,
+        );
+        assert_str_eq!(expected, get_transformed_content_default(input));
+    }
+}
+
+#[cfg(test)]
+mod test_rem2px {
+    use super::*;
+    use postcss_px2rem::transform::Rem2Px;
+
+    fn get_reversed_content(input: &str, option: Px2RemOption) -> String {
+        let mut rem_to_px = Rem2Px::new(option);
+        let writer = print_converted(
+            input,
+            &mut rem_to_px,
+            SimplePrettier::new(WrapString::default(), 4),
+        );
+        writer.writer.0
+    }
+
+    #[test]
+    fn test_replace_rem_with_px() {
+        let input =
+            ".rule { font-size: 0.9375rem; margin: 1rem; line-height: 1.2; letter-spacing: 2em; }";
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 15px;
+            margin: 1rem;
+            line-height: 1.2;
+            letter-spacing: 2em;
+        }
+        "#,
+        );
+        assert_str_eq!(expected, get_reversed_content(input, Default::default()));
+    }
+
+    #[test]
+    fn test_round_to_whole_pixels() {
+        let input = "@media (min-width: 31.25rem) { .rule { font-size: 0.55rem; width: 10rem; } }";
+        let expected = unindent(
+            r#"
+        @media (min-width: 500px) {
+            .rule {
+                font-size: 9px;
+                width: 160px;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_reversed_content(
+                input,
+                Px2RemOption {
+                    unit_precision: Some(0),
+                    media_query: Some(true),
+                    prop_list: Some(vec!["*".to_string()]),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_px2viewport {
+    use super::*;
+    use postcss_px2rem::transform::{Px2Viewport, Px2ViewportOption, ViewportUnit};
+
+    fn get_viewport_content(input: &str, option: Px2ViewportOption) -> String {
+        let mut px_to_viewport = Px2Viewport::new(option);
+        px_to_viewport.set_source(input);
+        let writer = print_converted(
+            input,
+            &mut px_to_viewport,
+            SimplePrettier::new(WrapString::default(), 4),
+        );
+        writer.writer.0
+    }
+
+    fn all_props() -> Px2RemOption {
+        Px2RemOption {
+            prop_list: Some(vec!["*".to_string()]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_replace_px_with_vw() {
+        let input = ".rule { width: 160px; margin: 0 32px; border: 1px solid; }";
+        let expected = unindent(
+            r#"
+        .rule {
+            width: 50vw;
+            margin: 0 10vw;
+            border: 0.3125vw solid;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_viewport_content(
+                input,
+                Px2ViewportOption {
+                    common: all_props(),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_viewport_unit_and_precision() {
+        let input = ".rule { height: 100px; }";
+        let expected = unindent(
+            r#"
+        .rule {
+            height: 13.333vmax;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_viewport_content(
+                input,
+                Px2ViewportOption {
+                    viewport_width: Some(750f64),
+                    viewport_height: Some(375f64),
+                    viewport_unit: Some(ViewportUnit::Vmax),
+                    common: Px2RemOption {
+                        unit_precision: Some(3),
+                        ..all_props()
+                    },
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_landscape_width() {
+        let input = ".a { width: 32px; } @media (orientation: landscape) { .b { width: 56.8px; } }";
+        let expected = unindent(
+            r#"
+        .a {
+            width: 10vw;
+        }
+        @media (orientation: landscape) {
+            .b {
+                width: 10vw;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_viewport_content(
+                input,
+                Px2ViewportOption {
+                    landscape_width: Some(568f64),
+                    common: all_props(),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_unit_converter {
+    use super::*;
+    use postcss_px2rem::transform::{SourceUnit, TargetUnit, UnitConverter, UnitConverterOption};
+
+    fn get_converted_content(input: &str, option: UnitConverterOption) -> String {
+        let mut converter = UnitConverter::new(option);
+        let writer = print_converted(
+            input,
+            &mut converter,
+            SimplePrettier::new(WrapString::default(), 4),
+        );
+        writer.writer.0
+    }
+
+    #[test]
+    fn test_pt_to_rem() {
+        let input = ".rule { font-size: 12pt; margin: 0 9pt 1in; width: 10px; }";
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 1rem;
+            margin: 0 0.75rem 1in;
+            width: 10px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_converted_content(
+                input,
+                UnitConverterOption {
+                    from: Some(SourceUnit::Pt),
+                    common: Px2RemOption {
+                        prop_list: Some(vec!["*".to_string()]),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_px_to_rpx_and_vw() {
+        let input = ".rule { width: 75px; }";
+        let option = |to| UnitConverterOption {
+            to: Some(to),
+            viewport_width: Some(375f64),
+            common: Px2RemOption {
+                prop_list: Some(vec!["*".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_str_eq!(
+            ".rule {\n    width: 150rpx;\n}\n",
+            get_converted_content(input, option(TargetUnit::Rpx))
+        );
+        assert_str_eq!(
+            ".rule {\n    width: 20vw;\n}\n",
+            get_converted_content(input, option(TargetUnit::Vw))
+        );
+    }
+
+    #[test]
+    fn test_px_to_vh() {
+        let input = ".rule { height: 284px; }";
+        let option = UnitConverterOption {
+            to: Some(TargetUnit::Vh),
+            common: Px2RemOption {
+                prop_list: Some(vec!["*".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_str_eq!(
+            ".rule {\n    height: 50vh;\n}\n",
+            get_converted_content(input, option)
+        );
+        assert_eq!("vmin".parse::<TargetUnit>(), Ok(TargetUnit::Vmin));
+    }
+
+    #[test]
+    fn test_same_defaults_as_px2viewport() {
+        use postcss_px2rem::transform::{Exclude, Px2Viewport, Px2ViewportOption, Rem2Px};
+        let input = ".rule { width: 32px; }";
+        let common = || Px2RemOption {
+            prop_list: Some(vec!["*".to_string()]),
+            exclude: Some(Exclude::String("vendor".to_string())),
+            ..Default::default()
+        };
+        let converter = UnitConverter::new(UnitConverterOption {
+            to: Some(TargetUnit::Vw),
+            common: common(),
+            ..Default::default()
+        });
+        let viewport = Px2Viewport::new(Px2ViewportOption {
+            common: common(),
+            ..Default::default()
+        });
+        let writer = print_converted(
+            input,
+            &mut viewport.clone(),
+            SimplePrettier::new(WrapString::default(), 4),
+        );
+        assert_str_eq!(
+            get_converted_content(
+                input,
+                UnitConverterOption {
+                    to: Some(TargetUnit::Vw),
+                    common: common(),
+                    ..Default::default()
+                }
+            ),
+            writer.writer.0
+        );
+        assert!(converter.is_excluded("vendor/a.css"));
+        assert!(viewport.is_excluded("vendor/a.css"));
+        assert!(Rem2Px::new(common()).is_excluded("vendor/a.css"));
+    }
+
+    #[test]
+    fn test_deserialize_units() {
+        let option: UnitConverterOption =
+            serde_json::from_str(r#"{ "from": "mm", "to": "em", "rootValue": 12 }"#).unwrap();
+        assert_eq!(option.from, Some(SourceUnit::Mm));
+        assert_eq!(option.to, Some(TargetUnit::Em));
+        assert_eq!(option.common.root_value, Some(12f64));
+    }
+}
+#[cfg(test)]
+mod test_media_query {
+    use super::*;
+
+    #[test]
+    fn test_replace_px_in_media_query() {
+        let input = "@media (min-width: 500px) { .rule { font-size: 16px } }";
+        let expected = unindent(
+            r#"
+        @media (min-width: 31.25rem) {
+            .rule {
+                font-size: 1rem;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    media_query: Some(true),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_replace_px_in_at_rule_list() {
+        let input = "@container card (min-width: 400px) { .a { font-size: 16px } } @supports (width: 10px) { .b { font-size: 16px } } @media (min-width: 500px) { .c { font-size: 16px } }";
+        let expected = unindent(
+            r#"
+        @container card (min-width: 25rem) {
+            .a {
+                font-size: 1rem;
+            }
+        }
+        @supports (width: 0.625rem) {
+            .b {
+                font-size: 1rem;
+            }
+        }
+        @media (min-width: 500px) {
+            .c {
+                font-size: 1rem;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    at_rule_list: Some(vec!["container".to_string(), "supports".to_string()]),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_print_custom_media_statements() {
+        let input =
+            "@custom-media --sm (max-width: 32px); @IMPORT url(x.css); .a { font-size: 16px }";
+        let option = || Px2RemOption {
+            at_rule_list: Some(vec!["custom-media".to_string()]),
+            ..Default::default()
+        };
+        let expected = unindent(
+            r#"
+        @custom-media --sm (max-width: 2rem);
+        @IMPORT url(x.css);
+        .a {
+            font-size: 1rem;
+        }
+        "#,
+        );
+        assert_str_eq!(expected, get_transformed_content_new(input, option()));
+        let mut px_to_rem = Px2Rem::new(option());
+        let writer = print_converted(
+            input,
+            &mut px_to_rem,
+            postcss_px2rem::transform::MinifiedPrinter::new(WrapString::default()),
+        );
+        assert_str_eq!(
+            "@custom-media --sm (max-width: 2rem);@IMPORT url(x.css);.a{font-size:1rem}",
+            writer.writer.0
+        );
+    }
+
+    #[test]
+    fn test_match_at_rule_names_ignoring_case() {
+        let input = "@MEDIA (min-width: 500px) { .a { font-size: 16px } } @Container (min-width: 400px) { .b { font-size: 16px } }";
+        let expected = unindent(
+            r#"
+        @MEDIA (min-width: 31.25rem) {
+            .a {
+                font-size: 1rem;
+            }
+        }
+        @Container (min-width: 25rem) {
+            .b {
+                font-size: 1rem;
+            }
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    media_query: Some(true),
+                    at_rule_list: Some(vec!["container".to_string()]),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_min_pixel_value {
+    use super::*;
+
+    #[test]
+    fn test_not_replace_value_below_min_pixel_value() {
+        let input = ".rule { border: 1px solid #000; font-size: 16px; margin: 1px 10px; }";
+        let expected = unindent(
+            r#"
+        .rule {
+            border: 1px solid #000;
+            font-size: 1rem;
+            margin: 1px 0.625rem;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    min_pixel_value: Some(2f64),
+                    prop_list: Some(vec!["*".to_string()]),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_filter_prop_list {
+    use super::*;
+
+    #[test]
+    fn test_exact() {
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            prop_list: Some(vec![
+                "font-size".to_string(),
+                "margin".to_string(),
+                "!padding".to_string(),
+                "*border*".to_string(),
+                "*".to_string(),
+                "*y".to_string(),
+                "!*font*".to_string(),
+            ]),
+            ..Default::default()
+        });
+        px_to_rem.generate_match_list();
+        assert_str_eq!(
+            px_to_rem.match_list().exact_list.join(","),
+            "font-size,margin"
+        );
+    }
+
+    #[test]
+    fn test_contain() {
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            prop_list: Some(vec![
+                "font-size".to_string(),
+                "*margin*".to_string(),
+                "!padding".to_string(),
+                "*border*".to_string(),
+                "*".to_string(),
+                "*y".to_string(),
+                "!*font*".to_string(),
+            ]),
+            ..Default::default()
+        });
+        px_to_rem.generate_match_list();
+        assert_str_eq!(
+            px_to_rem.match_list().contain_list.join(","),
+            "margin,border"
+        );
+    }
+
+    #[test]
+    fn test_start() {
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            prop_list: Some(vec![
+                "font-size".to_string(),
+                "*margin*".to_string(),
+                "!padding".to_string(),
+                "border*".to_string(),
+                "*".to_string(),
+                "*y".to_string(),
+                "!*font*".to_string(),
+            ]),
+            ..Default::default()
+        });
+        px_to_rem.generate_match_list();
+        assert_str_eq!(px_to_rem.match_list().starts_with_list.join(","), "border");
+    }
+
+    #[test]
+    fn test_end() {
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            prop_list: Some(vec![
+                "font-size".to_string(),
+                "*margin*".to_string(),
+                "!padding".to_string(),
+                "border*".to_string(),
+                "*".to_string(),
+                "*y".to_string(),
+                "!*font*".to_string(),
+            ]),
+            ..Default::default()
+        });
+        px_to_rem.generate_match_list();
+        assert_str_eq!(px_to_rem.match_list().ends_with_list.join(","), "y");
+    }
+
+    #[test]
+    fn test_not_exact() {
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            prop_list: Some(vec![
+                "font-size".to_string(),
+                "*margin*".to_string(),
+                "!padding".to_string(),
+                "border*".to_string(),
+                "*".to_string(),
+                "*y".to_string(),
+                "!*font*".to_string(),
+            ]),
+            ..Default::default()
+        });
+        px_to_rem.generate_match_list();
+        assert_str_eq!(px_to_rem.match_list().not_exact_list.join(","), "padding");
+    }
+
+    #[test]
+    fn test_not_start() {
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            prop_list: Some(vec![
+                "font-size".to_string(),
+                "*margin*".to_string(),
+                "!padding".to_string(),
+                "border*".to_string(),
+                "*".to_string(),
+                "*y".to_string(),
+                "!*font*".to_string(),
+            ]),
+            ..Default::default()
+        });
+        px_to_rem.generate_match_list();
+        assert_str_eq!(px_to_rem.match_list().not_contain_list.join(","), "font");
+    }
+
+    #[test]
+    fn test_not_contain() {
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            prop_list: Some(vec![
+                "font-size".to_string(),
+                "*margin*".to_string(),
+                "!padding".to_string(),
+                "border*".to_string(),
+                "*".to_string(),
+                "*y".to_string(),
+                "!*font*".to_string(),
+            ]),
+            ..Default::default()
+        });
+        px_to_rem.generate_match_list();
+        assert_str_eq!(px_to_rem.match_list().not_contain_list.join(","), "font");
+    }
+
+    #[test]
+    fn test_not_end() {
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            prop_list: Some(vec![
+                "font-size".to_string(),
+                "*margin*".to_string(),
+                "!padding".to_string(),
+                "!border*".to_string(),
+                "*".to_string(),
+                "!*y".to_string(),
+                "!*font*".to_string(),
             ]),
             ..Default::default()
         });
-        px_to_rem.generate_match_list();
-        assert_str_eq!(px_to_rem.match_list.not_contain_list.join(","), "font");
+        px_to_rem.generate_match_list();
+        assert_str_eq!(px_to_rem.match_list().not_ends_list.join(","), "y");
+    }
+}
+
+#[cfg(test)]
+mod test_exclude {
+    use super::*;
+    use postcss_px2rem::transform::Exclude;
+
+    #[test]
+    fn test_ignore_file_path_with_exclude_regexp() {
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 15px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_from(
+                BASIC_CSS,
+                Px2RemOption {
+                    exclude: Some(Exclude::Regexp("(?i)exclude".to_string())),
+                    ..Default::default()
+                },
+                "exclude/path"
+            )
+        );
+    }
+
+    #[test]
+    fn test_ignore_file_path_with_exclude_string() {
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 15px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_from(
+                BASIC_CSS,
+                Px2RemOption {
+                    exclude: Some(Exclude::String("exclude".to_string())),
+                    ..Default::default()
+                },
+                "exclude/path"
+            )
+        );
+    }
+
+    #[test]
+    fn test_exclude_from_config() {
+        let option = |json: &str| serde_json::from_str::<Px2RemOption>(json).unwrap().exclude;
+        assert!(matches!(
+            option(r#"{ "exclude": "node_modules" }"#),
+            Some(Exclude::String(string)) if string == "node_modules"
+        ));
+        assert!(matches!(
+            option(r#"{ "exclude": "/node_modules/i" }"#),
+            Some(Exclude::Regexp(re)) if re == "(?i)node_modules"
+        ));
+        assert!(matches!(
+            option(r#"{ "exclude": { "glob": "**/node_modules/**" } }"#),
+            Some(Exclude::Glob(glob)) if glob == "**/node_modules/**"
+        ));
+    }
+
+    #[test]
+    fn test_ignore_file_path_with_exclude_glob() {
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 15px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_from(
+                BASIC_CSS,
+                Px2RemOption {
+                    exclude: Some(Exclude::Glob("**/node_modules/**".to_string())),
+                    ..Default::default()
+                },
+                "src/node_modules/lib/index.css"
+            )
+        );
+    }
+
+    #[test]
+    fn test_ignore_file_path_with_exclude_function() {
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 15px;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_from(
+                BASIC_CSS,
+                Px2RemOption {
+                    exclude: Some(Exclude::Function(Box::new(|file| file.contains("exclude")))),
+                    ..Default::default()
+                },
+                "exclude/path"
+            )
+        );
+    }
+
+    #[test]
+    fn test_not_ignore_file_path_without_match() {
+        let expected = unindent(
+            r#"
+        .rule {
+            font-size: 0.9375rem;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_from(
+                BASIC_CSS,
+                Px2RemOption {
+                    exclude: Some(Exclude::Regexp("(?i)exclude".to_string())),
+                    ..Default::default()
+                },
+                "src/path"
+            )
+        );
+    }
+}
+
+/// Run `converter` over `input` and print the converted root with `printer`
+fn print_converted<'a, P>(input: &'a str, converter: &mut impl VisitMut<'a>, mut printer: P) -> P
+where
+    P: VisitMut<'a, std::io::Result<()>>,
+{
+    let mut root = parse(input, None);
+    converter.visit_root(&mut root);
+    printer.visit_root(&mut root).unwrap();
+    printer
+}
+
+fn get_transformed_content_default(input: &str) -> String {
+    let mut px_to_rem = Px2Rem::default();
+    px_to_rem.generate_match_list();
+    px_to_rem.set_source(input);
+    let writer = print_converted(
+        input,
+        &mut px_to_rem,
+        SimplePrettier::new(WrapString::default(), 4),
+    );
+    writer.writer.0
+}
+
+fn get_transformed_content_new(input: &str, option: Px2RemOption) -> String {
+    let mut px_to_rem = Px2Rem::new(option);
+    px_to_rem.set_source(input);
+    let writer = print_converted(
+        input,
+        &mut px_to_rem,
+        SimplePrettier::new(WrapString::default(), 4),
+    );
+    writer.writer.0
+}
+
+fn get_transformed_content_from(input: &str, option: Px2RemOption, from: &str) -> String {
+    let mut px_to_rem = Px2Rem::new(option);
+    px_to_rem.set_from(Some(from));
+    let writer = print_converted(
+        input,
+        &mut px_to_rem,
+        SimplePrettier::new(WrapString::default(), 4),
+    );
+    writer.writer.0
+}
+
+#[cfg(test)]
+mod test_source_map {
+    use super::*;
+    use postcss_px2rem::source_map::{find_url, SourceMap, SourceMapBuilder};
+
+    fn get_source_map(input: &str) -> (String, SourceMap) {
+        let mut px_to_rem = Px2Rem::new(Default::default());
+        let mut writer = print_converted(
+            input,
+            &mut px_to_rem,
+            SimplePrettier::new(WrapString::default(), 4)
+                .with_source_map(SourceMapBuilder::new("style.css", input)),
+        );
+        let map = writer.source_map(Some("style.min.css")).unwrap();
+        (writer.writer.0, map)
+    }
+
+    #[test]
+    fn test_map_every_node() {
+        let input = ".a { font-size: 16px; }\n.b {\n  color: red;\n}";
+        let (output, map) = get_source_map(input);
+        let expected = unindent(
+            r#"
+        .a {
+            font-size: 1rem;
+        }
+        .b {
+            color: red;
+        }
+        "#,
+        );
+        assert_str_eq!(expected, output);
+        assert_eq!(map.mappings, "AAAA;IAAK;;AACL;IACE");
+        assert_eq!(map.sources, vec!["style.css".to_string()]);
+        assert_eq!(map.file.as_deref(), Some("style.min.css"));
+        assert_eq!(SourceMap::from_json(&map.to_json()).unwrap(), map);
+    }
+
+    #[test]
+    fn test_chain_incoming_map() {
+        let (_, map) = get_source_map(".a { font-size: 16px; }");
+        let incoming = SourceMap::from_json(
+            r#"{"version":3,"sources":["a.scss"],"names":[],"mappings":"AAUA,KAAK"}"#,
+        )
+        .unwrap();
+        let chained = map.chain(&incoming).unwrap();
+        assert_eq!(chained.sources, vec!["a.scss".to_string()]);
+        assert_eq!(chained.mappings, "AAUA;IAAK");
+    }
+
+    #[test]
+    fn test_inline_comment() {
+        let (_, map) = get_source_map(".a { font-size: 16px; }");
+        let comment = map.to_inline_comment();
+        let url = find_url(&comment).unwrap();
+        assert_eq!(SourceMap::from_data_url(url).unwrap(), map);
+        assert!(SourceMap::from_json(r#"{"version":3,"mappings":"A$"}"#).is_err());
+        assert!(SourceMap::from_json(r#"{"version":3,"mappings":"hgggggggggggI"}"#).is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_lossless {
+    use super::*;
+    use postcss_px2rem::transform::LosslessPrinter;
+
+    fn get_lossless_content(input: &str, option: Px2RemOption) -> String {
+        let mut px_to_rem = Px2Rem::new(option);
+        px_to_rem.set_source(input);
+        let writer = print_converted(
+            input,
+            &mut px_to_rem,
+            LosslessPrinter::new(WrapString::default(), input),
+        );
+        writer.writer.0
+    }
+
+    #[test]
+    fn test_keep_formatting_and_comments() {
+        let input = "/* header */\n.rule{font-size:16px;   margin : 0 0 20px}\n\n@media (min-width: 320px) {\n  .a { font-size : 32px !important }\n}\n";
+        let expected = "/* header */\n.rule{font-size:1rem;   margin : 0 0 20px}\n\n@media (min-width: 20rem) {\n  .a { font-size : 2rem !important }\n}\n";
+        assert_str_eq!(
+            expected,
+            get_lossless_content(
+                input,
+                Px2RemOption {
+                    media_query: Some(true),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_insert_fallback_after_original() {
+        let input = ".a {\n  font-size: 16px; /* px */\n  line-height: 32px\n}\n";
+        let expected = ".a {\n  font-size: 16px; /* px */\n  font-size: 1rem;\n  line-height: 32px;\n  line-height: 2rem\n}\n";
+        assert_str_eq!(
+            expected,
+            get_lossless_content(
+                input,
+                Px2RemOption {
+                    replace: Some(false),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_split_rule_after_original() {
+        use postcss_px2rem::transform::StringOrRegexp;
+        let input = ".keep,\n.ignore { font-size: 16px }\n.b { font-size: 32px }\n";
+        let expected =
+            ".keep { font-size: 1rem }\n.ignore { font-size: 16px }\n.b { font-size: 2rem }\n";
+        assert_str_eq!(
+            expected,
+            get_lossless_content(
+                input,
+                Px2RemOption {
+                    selector_black_list: Some(vec![StringOrRegexp::String(".ignore".to_string())]),
+                    split_selector_list: Some(true),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_keep_comments_and_spacing_in_values() {
+        let input = ".a { margin: 16px /* was 8px */  32px ! important; }\n@media (min-width: 320px) /* 20em */ {}\n";
+        let expected = ".a { margin: 1rem /* was 8px */  2rem ! important; }\n@media (min-width: 20rem) /* 20em */ {}\n";
+        assert_str_eq!(
+            expected,
+            get_lossless_content(
+                input,
+                Px2RemOption {
+                    prop_list: Some(vec!["*".to_string()]),
+                    media_query: Some(true),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_untouched_source() {
+        let input = "a{color:red}/* 10px */\n.b { width: 10px; font-size: 10px /* no */; }";
+        assert_str_eq!(input, get_lossless_content(input, Default::default()));
+    }
+}
+
+#[cfg(test)]
+mod test_minified {
+    use super::*;
+    use postcss_px2rem::transform::MinifiedPrinter;
+
+    fn get_minified_content(input: &str, option: Px2RemOption) -> String {
+        let mut px_to_rem = Px2Rem::new(option);
+        let writer = print_converted(
+            input,
+            &mut px_to_rem,
+            MinifiedPrinter::new(WrapString::default()),
+        );
+        writer.writer.0
+    }
+
+    #[test]
+    fn test_minify_rules() {
+        let input = unindent(
+            r#"
+        .a,
+        .b  > .c {
+            margin: 8px   0 -4px;
+            font-family: "Helvetica  Neue";
+            color: red !important;
+        }
+        @media (min-width: 320px) {
+            .d { font-size: 10.5px; }
+        }
+        "#,
+        );
+        let expected = r#".a,.b > .c{margin:.5rem 0 -.25rem;font-family:"Helvetica  Neue";color:red!important}@media (min-width: 20rem){.d{font-size:.65625rem}}"#;
+        assert_str_eq!(
+            expected,
+            get_minified_content(
+                &input,
+                Px2RemOption {
+                    prop_list: Some(vec!["*".to_string()]),
+                    media_query: Some(true),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_keep_important_inside_strings() {
+        let input = r#".a { content: "x !important"; background: url(a !important.png) ; margin: 0 ! IMPORTANT; }"#;
+        let expected =
+            r#".a{content:"x !important";background:url(a !important.png);margin:0!IMPORTANT}"#;
+        assert_str_eq!(
+            expected,
+            get_minified_content(input, Px2RemOption::default())
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_at_rule {
+    use super::*;
+
+    #[test]
+    fn test_print_declarations_in_at_rules() {
+        let input =
+            "@font-face { font-family: Foo; src: url(foo.woff); } @page :first { margin: 16px; }";
+        let expected = unindent(
+            r#"
+        @font-face {
+            font-family: Foo;
+            src: url(foo.woff);
+        }
+        @page :first {
+            margin: 1rem;
+        }
+        "#,
+        );
+        assert_str_eq!(
+            expected,
+            get_transformed_content_new(
+                input,
+                Px2RemOption {
+                    prop_list: Some(vec!["*".to_string()]),
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_print_bodiless_at_rules() {
+        let input =
+            r#"@charset "utf-8"; @import url(x.css) screen; @layer base; .a { font-size: 16px; }"#;
+        let expected = unindent(
+            r#"
+        @charset "utf-8";
+        @import url(x.css) screen;
+        @layer base;
+        .a {
+            font-size: 1rem;
+        }
+        "#,
+        );
+        assert_str_eq!(expected, get_transformed_content_default(input));
+    }
+}
+
+#[cfg(test)]
+mod test_stats {
+    use super::*;
+    use postcss_px2rem::stats::Stats;
+
+    #[test]
+    fn test_count_converted_and_skipped() {
+        let input = unindent(
+            r#"
+        .a { font-size: 16px; line-height: 1PX; margin: 8px; }
+        .b { font: 12px/24px "10px", url(a-2px.png); letter-spacing: 1px; }
+        .ignore { font-size: 16px; }
+        .c { font-size: 16px; /* no */ }
+        "#,
+        );
+        let mut px_to_rem = Px2Rem::new(Px2RemOption {
+            min_pixel_value: Some(2f64),
+            selector_black_list: Some(vec![postcss_px2rem::transform::StringOrRegexp::String(
+                ".ignore".to_string(),
+            )]),
+            ..Default::default()
+        });
+        px_to_rem.set_source(&input);
+        let mut root = parse(&input, None);
+        px_to_rem.visit_root(&mut root);
+        let stats = px_to_rem.take_stats();
+        assert_eq!(stats.declarations, 7);
+        assert_eq!(stats.converted, 3);
+        assert_eq!(stats.skipped.not_in_prop_list, 1);
+        assert_eq!(stats.skipped.below_min_pixel_value, 1);
+        assert_eq!(stats.skipped.blacklisted_selector, 1);
+        assert_eq!(stats.skipped.uppercase, 1);
+        assert_eq!(stats.skipped.url_var_or_string, 2);
+        assert_eq!(stats.skipped.disabled, 1);
+        assert_eq!(stats.properties["font-size"], 1);
+        assert_eq!(stats.properties["font"], 2);
+        assert_eq!(px_to_rem.stats(), Default::default());
+    }
+
+    fn get_stats(input: &str, option: Px2RemOption) -> Stats {
+        let mut px_to_rem = Px2Rem::new(option);
+        px_to_rem.visit_root(&mut parse(input, None));
+        px_to_rem.take_stats()
     }
 
     #[test]
-    fn test_not_contain() {
+    fn test_count_nothing_for_existing_declaration() {
+        let stats = get_stats(
+            ".a { margin: 1px 16px; margin: 1px 1rem; }",
+            Px2RemOption {
+                prop_list: Some(vec!["*".to_string()]),
+                min_pixel_value: Some(2f64),
+                ..Default::default()
+            },
+        );
+        assert_eq!(stats.declarations, 2);
+        assert_eq!(stats.converted, 0);
+        // only the 1px of the existing declaration
+        assert_eq!(stats.skipped.below_min_pixel_value, 1);
+        assert!(stats.properties.is_empty());
+    }
+
+    #[test]
+    fn test_count_below_min_pixel_value() {
+        let stats = get_stats(
+            ".a { margin: 1px 16px; border-width: 1px; }",
+            Px2RemOption {
+                prop_list: Some(vec!["*".to_string()]),
+                min_pixel_value: Some(2f64),
+                ..Default::default()
+            },
+        );
+        assert_eq!(stats.converted, 1);
+        assert_eq!(stats.skipped.below_min_pixel_value, 2);
+        assert_eq!(stats.properties["margin"], 1);
+    }
+
+    #[test]
+    fn test_count_px_values_in_url_var_or_string() {
+        let stats = get_stats(
+            r#".a { background: url(px.png), url(a-16px.png); width: var(--px, 8px); content: "px 1px 2px"; }"#,
+            Px2RemOption {
+                prop_list: Some(vec!["*".to_string()]),
+                ..Default::default()
+            },
+        );
+        assert_eq!(stats.converted, 0);
+        assert_eq!(stats.skipped.url_var_or_string, 4);
+    }
+
+    #[test]
+    fn test_count_at_rule_params() {
+        let stats = get_stats(
+            "@media (min-width: 320px) and (max-width: 1px) { .a { color: red; } }",
+            Px2RemOption {
+                media_query: Some(true),
+                min_pixel_value: Some(2f64),
+                ..Default::default()
+            },
+        );
+        assert_eq!(stats.converted, 1);
+        assert_eq!(stats.skipped.below_min_pixel_value, 1);
+    }
+
+    #[test]
+    fn test_count_zero_alike_in_every_direction() {
+        let option = || Px2RemOption {
+            prop_list: Some(vec!["*".to_string()]),
+            min_pixel_value: Some(2f64),
+            ..Default::default()
+        };
+        let mut px_to_rem = Px2Rem::new(option());
+        px_to_rem.visit_root(&mut parse(".a { margin: 0px 1px 16px; }", None));
+        let mut rem_to_px = postcss_px2rem::transform::Rem2Px::new(option());
+        rem_to_px.visit_root(&mut parse(".a { margin: 0rem 0.0625rem 1rem; }", None));
+        for stats in [px_to_rem.take_stats(), rem_to_px.take_stats()] {
+            assert_eq!(stats.converted, 1);
+            assert_eq!(stats.skipped.below_min_pixel_value, 1);
+        }
+    }
+
+    #[test]
+    fn test_px_replace_does_not_count() {
+        let px_to_rem = Px2Rem::new(Px2RemOption {
+            min_pixel_value: Some(2f64),
+            ..Default::default()
+        });
+        assert_eq!(
+            px_to_rem.px_replace("1px 16px url(2px.png)"),
+            "1px 1rem url(2px.png)"
+        );
+        assert_eq!(px_to_rem.rem_replace("1rem"), "16px");
+        assert_eq!(px_to_rem.stats(), Default::default());
+    }
+}
+
+#[cfg(test)]
+mod test_diff {
+    use super::*;
+    use postcss_px2rem::diff::unified_diff;
+    use postcss_px2rem::transform::LosslessPrinter;
+
+    fn get_diff(input: &str, option: Px2RemOption) -> String {
+        let mut px_to_rem = Px2Rem::new(option);
+        let writer = print_converted(
+            input,
+            &mut px_to_rem,
+            LosslessPrinter::new(std::io::sink(), input),
+        );
+        unified_diff("style.css", input, writer.edits())
+    }
+
+    #[test]
+    fn test_hunk_per_declaration() {
+        let input = ".a {\n  font-size: 16px;\n  color: red;\n}\n.b {\n  font-size: 32px;\n}";
+        let expected = r#"--- a/style.css
++++ b/style.css
+@@ -2 +2 @@
+-  font-size: 16px;
++  font-size: 1rem;
+@@ -6 +6 @@
+-  font-size: 32px;
++  font-size: 2rem;
+"#;
+        assert_str_eq!(expected, get_diff(input, Default::default()));
+    }
+
+    #[test]
+    fn test_inserted_lines_shift_hunks() {
+        let input = ".a {\n  font-size: 16px;\n}\n.b { font-size: 32px; }";
+        let expected = r#"--- a/style.css
++++ b/style.css
+@@ -2 +2,2 @@
+-  font-size: 16px;
++  font-size: 16px;
++  font-size: 1rem;
+@@ -4 +5 @@
+-.b { font-size: 32px; }
+\ No newline at end of file
++.b { font-size: 32px; font-size: 2rem; }
+\ No newline at end of file
+"#;
+        assert_str_eq!(
+            expected,
+            get_diff(
+                input,
+                Px2RemOption {
+                    replace: Some(false),
+                    ..Default::default()
+                }
+            )
+        );
+        assert_str_eq!("", get_diff(".a { color: red; }", Default::default()));
+    }
+}
+
+#[cfg(test)]
+mod test_lint {
+    use super::*;
+
+    #[test]
+    fn test_report_values_to_convert() {
+        let input = unindent(
+            r#"
+        .a {
+            font-size: 16px;
+            margin: 8px;
+            line-height: 1px;
+        }
+        .ignore { font-size: 16px; }
+        /* px2rem-disable-next-line */
+        .b { font-size: 16px; }
+        .c { letter-spacing: 0.5px; font-size: 32px !important; }
+        "#,
+        );
         let mut px_to_rem = Px2Rem::new(Px2RemOption {
-            prop_list: Some(vec![
-                "font-size".to_string(),
-                "*margin*".to_string(),
-                "!padding".to_string(),
-                "border*".to_string(),
-                "*".to_string(),
-                "*y".to_string(),
-                "!*font*".to_string(),
-            ]),
+            min_pixel_value: Some(2f64),
+            selector_black_list: Some(vec![postcss_px2rem::transform::StringOrRegexp::String(
+                ".ignore".to_string(),
+            )]),
             ..Default::default()
         });
-        px_to_rem.generate_match_list();
-        assert_str_eq!(px_to_rem.match_list.not_contain_list.join(","), "font");
+        let mut root = parse(&input, None);
+        let violations = px_to_rem
+            .lint(&mut root, &input)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            vec![
+                "2:5: font-size: 16px, use 1rem",
+                "9:29: font-size: 32px !important, use 2rem !important",
+            ]
+        );
     }
 
     #[test]
-    fn test_not_end() {
+    fn test_report_at_rule_params_without_converting() {
+        let input = "@media (min-width: 320px) { .a, .ignore { font-size: 16px; } }";
         let mut px_to_rem = Px2Rem::new(Px2RemOption {
-            prop_list: Some(vec![
-                "font-size".to_string(),
-                "*margin*".to_string(),
-                "!padding".to_string(),
-                "!border*".to_string(),
-                "*".to_string(),
-                "!*y".to_string(),
-                "!*font*".to_string(),
-            ]),
+            media_query: Some(true),
+            selector_black_list: Some(vec![postcss_px2rem::transform::StringOrRegexp::String(
+                ".ignore".to_string(),
+            )]),
+            split_selector_list: Some(true),
             ..Default::default()
         });
-        px_to_rem.generate_match_list();
-        assert_str_eq!(px_to_rem.match_list.not_ends_list.join(","), "y");
+        let mut root = parse(input, None);
+        let violations = px_to_rem
+            .lint(&mut root, input)
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            vec![
+                "1:1: @media: (min-width: 320px), use (min-width: 20rem)",
+                "1:43: font-size: 16px, use 1rem",
+            ]
+        );
+        let mut writer = SimplePrettier::new(WrapString::default(), 0);
+        writer.visit_root(&mut root).unwrap();
+        assert_str_eq!(
+            "@media (min-width: 320px) {\n.a, .ignore {\nfont-size: 16px;\n}\n}\n",
+            writer.writer.0
+        );
     }
 }
 
-// these test case should handled by cli or node binding
-// describe("exclude", function() {
-//   it("should ignore file path with exclude RegEx", function() {
-//     var options = {
-//       exclude: /exclude/i
-//     };
-//     var processed = postcss(pxtorem(options)).process(basicCSS, {
-//       from: "exclude/path"
-//     }).css;
-//     expect(processed).toBe(basicCSS);
-//   });
-
-//   it("should not ignore file path with exclude String", function() {
-//     var options = {
-//       exclude: "exclude"
-//     };
-//     var processed = postcss(pxtorem(options)).process(basicCSS, {
-//       from: "exclude/path"
-//     }).css;
-//     expect(processed).toBe(basicCSS);
-//   });
-
-//   it("should not ignore file path with exclude function", function() {
-//     var options = {
-//       exclude: function(file) {
-//         return file.indexOf("exclude") !== -1;
-//       }
-//     };
-//     var processed = postcss(pxtorem(options)).process(basicCSS, {
-//       from: "exclude/path"
-//     }).css;
-//     expect(processed).toBe(basicCSS);
-//   });
-// });
-
-fn get_transformed_content_default(input: &str) -> String {
-    let mut root = parse(input, None);
+#[cfg(test)]
+mod test_batch {
+    use super::*;
+    use postcss_px2rem::batch::par_map;
 
-    let mut px_to_rem = Px2Rem::default();
-    px_to_rem.generate_match_list();
-    px_to_rem.visit_root(&mut root);
-    let wrap_string = WrapString::default();
-    let mut writer = SimplePrettier::new(wrap_string, 4);
-    writer.visit_root(&mut root).unwrap();
-    writer.writer.0
-}
+    #[test]
+    fn test_converter_is_send() {
+        fn assert_send<T: Send + Clone>() {}
+        assert_send::<Px2Rem>();
+        assert_send::<postcss_px2rem::transform::UnitConverter>();
+    }
 
-fn get_transformed_content_new(input: &str, option: Px2RemOption) -> String {
-    let mut root = parse(input, None);
+    #[test]
+    fn test_results_keep_input_order() {
+        let inputs = (1..=64)
+            .map(|size| format!(".rule {{ font-size: {}px; }}", size * 16))
+            .collect::<Vec<_>>();
+        let px_to_rem = Px2Rem::new(Px2RemOption {
+            root_value_resolver: Some(Box::new(|_| 16f64)),
+            ..Default::default()
+        });
+        let outputs = par_map(px_to_rem, &inputs, |px_to_rem, input| {
+            let mut root = parse(input, None);
+            px_to_rem.visit_root(&mut root);
+            let mut writer = SimplePrettier::new(WrapString::default(), 0);
+            writer.visit_root(&mut root).unwrap();
+            (writer.writer.0, px_to_rem.take_stats().converted)
+        });
+        for (size, (output, converted)) in (1..=64).zip(outputs) {
+            assert_eq!(output, format!(".rule {{\nfont-size: {}rem;\n}}\n", size));
+            assert_eq!(converted, 1);
+        }
+    }
 
-    let mut px_to_rem = Px2Rem::new(option);
-    px_to_rem.visit_root(&mut root);
-    let wrap_string = WrapString::default();
-    let mut writer = SimplePrettier::new(wrap_string, 4);
-    writer.visit_root(&mut root).unwrap();
-    writer.writer.0
+    #[test]
+    fn test_clone_starts_without_stats() {
+        let mut px_to_rem = Px2Rem::new(Default::default());
+        px_to_rem.visit_root(&mut parse(".rule { font-size: 16px; }", None));
+        assert_eq!(px_to_rem.stats().converted, 1);
+        let outputs = par_map(
+            px_to_rem.clone(),
+            &[".a { color: red; }"],
+            |clone, input| {
+                clone.visit_root(&mut parse(input, None));
+                clone.take_stats()
+            },
+        );
+        assert_eq!(outputs[0].declarations, 1);
+        assert_eq!(outputs[0].converted, 0);
+        assert_eq!(px_to_rem.stats().converted, 1);
+    }
 }